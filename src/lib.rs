@@ -1,26 +1,52 @@
 pub mod render_math {
     pub mod vector {
         use super::matrix::Mat4;
-        use std::ops::{Mul, Add, Sub};
+        use std::ops::{Mul, Add, Sub, Div, Neg, AddAssign, SubAssign, MulAssign, DivAssign, Index, IndexMut};
 
         pub trait Magnitude {
             fn magnitude(&self) -> f64;
         }
 
-        #[derive(Debug, Copy, Clone)]
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
         pub struct Vector2i {
             x: i32,
             y: i32,
         }
 
-        #[derive(Debug, Copy, Clone)]
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct Vector2 {
+            x: f64,
+            y: f64,
+        }
+
+        // How Vector2 components are turned into integers
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum Rounding {
+            Floor,
+            Ceil,
+            Round,
+            Truncate,
+        }
+
+        impl Rounding {
+            fn apply(&self, v: f64) -> i32 {
+                match self {
+                    Rounding::Floor => v.floor() as i32,
+                    Rounding::Ceil => v.ceil() as i32,
+                    Rounding::Round => v.round() as i32,
+                    Rounding::Truncate => v.trunc() as i32,
+                }
+            }
+        }
+
+        #[derive(Debug, Copy, Clone, PartialEq)]
         pub struct Vector3 {
             x: f64,
             y: f64,
             z: f64,
         }
 
-        #[derive(Debug, Copy, Clone)]
+        #[derive(Debug, Copy, Clone, PartialEq)]
         pub struct Vector4 {
             x: f64,
             y: f64,
@@ -49,6 +75,13 @@ pub mod render_math {
             pub fn mut_y(&mut self) -> &mut i32 {
                 &mut self.y
             }
+
+            pub fn from(vec: &Vector2, rounding: Rounding) -> Self {
+                Vector2i {
+                    x: rounding.apply(vec.x),
+                    y: rounding.apply(vec.y)
+                }
+            }
         }
 
         impl Add for Vector2i {
@@ -78,12 +111,244 @@ pub mod render_math {
 
             fn mul(self, rhs: Self) -> Self::Output {
                 Vector2i {
+                    x: self.x * rhs.x,
+                    y: self.y * rhs.y
+                }
+            }
+        }
+
+        impl Mul<i32> for Vector2i {
+            type Output = Vector2i;
+
+            fn mul(self, rhs: i32) -> Self::Output {
+                Vector2i {
+                    x: self.x * rhs,
+                    y: self.y * rhs
+                }
+            }
+        }
+
+        impl Neg for Vector2i {
+            type Output = Vector2i;
+
+            fn neg(self) -> Self::Output {
+                Vector2i {
+                    x: -self.x,
+                    y: -self.y
+                }
+            }
+        }
+
+        impl AddAssign for Vector2i {
+            fn add_assign(&mut self, rhs: Self) {
+                self.x += rhs.x;
+                self.y += rhs.y;
+            }
+        }
+
+        impl SubAssign for Vector2i {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.x -= rhs.x;
+                self.y -= rhs.y;
+            }
+        }
+
+        impl Vector2 {
+            pub fn new(x: f64, y: f64) -> Self {
+                Vector2 {
+                    x,
+                    y,
+                }
+            }
+
+            pub fn from(vec: &Vector2i) -> Self {
+                Vector2 {
+                    x: f64::from(vec.x),
+                    y: f64::from(vec.y)
+                }
+            }
+
+            pub fn x(&self) -> f64 {
+                self.x
+            }
+            pub fn y(&self) -> f64 {
+                self.y
+            }
+
+            pub fn mut_x(&mut self) -> &mut f64 {
+                &mut self.x
+            }
+            pub fn mut_y(&mut self) -> &mut f64 {
+                &mut self.y
+            }
+
+            pub fn dot(&self, other: &Vector2) -> f64 {
+                self.x * other.x + self.y * other.y
+            }
+
+            // Z component of the 3D cross product, twice the signed area of the triangle (0, self, other)
+            pub fn perp_dot(&self, other: &Vector2) -> f64 {
+                self.x * other.y - self.y * other.x
+            }
+
+            // Zero vector stays zero instead of turning into NaN
+            pub fn normalize(&self) -> Vector2 {
+                let length = self.magnitude();
+                if length == 0.0 {
+                    return *self;
+                }
+                *self / length
+            }
+
+            pub fn lerp(&self, other: &Vector2, t: f64) -> Vector2 {
+                *self + (*other - *self) * t
+            }
+
+            pub fn distance(&self, other: &Vector2) -> f64 {
+                (*other - *self).magnitude()
+            }
+
+            pub fn min(&self, other: &Vector2) -> Vector2 {
+                Vector2::new(self.x.min(other.x), self.y.min(other.y))
+            }
+
+            pub fn max(&self, other: &Vector2) -> Vector2 {
+                Vector2::new(self.x.max(other.x), self.y.max(other.y))
+            }
+
+            pub fn approx_eq(&self, other: &Vector2, epsilon: f64) -> bool {
+                (self.x - other.x).abs() <= epsilon &&
+                (self.y - other.y).abs() <= epsilon
+            }
+        }
+
+        impl Add for Vector2 {
+            type Output = Vector2;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Vector2 {
+                    x: self.x + rhs.x,
+                    y: self.y + rhs.y
+                }
+            }
+        }
+
+        impl Sub for Vector2 {
+            type Output = Vector2;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Vector2 {
                     x: self.x - rhs.x,
                     y: self.y - rhs.y
                 }
             }
         }
 
+        impl Mul for Vector2 {
+            type Output = Vector2;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                Vector2 {
+                    x: self.x * rhs.x,
+                    y: self.y * rhs.y
+                }
+            }
+        }
+
+        impl Mul<f64> for Vector2 {
+            type Output = Vector2;
+
+            fn mul(self, rhs: f64) -> Self::Output {
+                Vector2 {
+                    x: self.x * rhs,
+                    y: self.y * rhs
+                }
+            }
+        }
+
+        impl Div for Vector2 {
+            type Output = Vector2;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                Vector2 {
+                    x: self.x / rhs.x,
+                    y: self.y / rhs.y
+                }
+            }
+        }
+
+        impl Div<f64> for Vector2 {
+            type Output = Vector2;
+
+            fn div(self, rhs: f64) -> Self::Output {
+                Vector2 {
+                    x: self.x / rhs,
+                    y: self.y / rhs
+                }
+            }
+        }
+
+        impl Neg for Vector2 {
+            type Output = Vector2;
+
+            fn neg(self) -> Self::Output {
+                Vector2 {
+                    x: -self.x,
+                    y: -self.y
+                }
+            }
+        }
+
+        impl AddAssign for Vector2 {
+            fn add_assign(&mut self, rhs: Self) {
+                self.x += rhs.x;
+                self.y += rhs.y;
+            }
+        }
+
+        impl SubAssign for Vector2 {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.x -= rhs.x;
+                self.y -= rhs.y;
+            }
+        }
+
+        impl MulAssign<f64> for Vector2 {
+            fn mul_assign(&mut self, rhs: f64) {
+                self.x *= rhs;
+                self.y *= rhs;
+            }
+        }
+
+        impl DivAssign<f64> for Vector2 {
+            fn div_assign(&mut self, rhs: f64) {
+                self.x /= rhs;
+                self.y /= rhs;
+            }
+        }
+
+        impl Index<usize> for Vector2 {
+            type Output = f64;
+
+            fn index(&self, index: usize) -> &Self::Output {
+                match index {
+                    0 => &self.x,
+                    1 => &self.y,
+                    _ => panic!("Vector2 index {index} out of range."),
+                }
+            }
+        }
+
+        impl IndexMut<usize> for Vector2 {
+            fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+                match index {
+                    0 => &mut self.x,
+                    1 => &mut self.y,
+                    _ => panic!("Vector2 index {index} out of range."),
+                }
+            }
+        }
+
         impl Vector3 {
             pub fn new(x: f64, y: f64, z: f64) -> Self {
                 Vector3 {
@@ -120,6 +385,49 @@ pub mod render_math {
             pub fn mut_z(&mut self) -> &mut f64 {
                 &mut self.z
             }
+
+            pub fn dot(&self, other: &Vector3) -> f64 {
+                self.x * other.x + self.y * other.y + self.z * other.z
+            }
+
+            pub fn cross(&self, other: &Vector3) -> Vector3 {
+                Vector3 {
+                    x: self.y * other.z - self.z * other.y,
+                    y: self.z * other.x - self.x * other.z,
+                    z: self.x * other.y - self.y * other.x
+                }
+            }
+
+            // Zero vector stays zero instead of turning into NaN
+            pub fn normalize(&self) -> Vector3 {
+                let length = self.magnitude();
+                if length == 0.0 {
+                    return *self;
+                }
+                *self / length
+            }
+
+            pub fn lerp(&self, other: &Vector3, t: f64) -> Vector3 {
+                *self + (*other - *self) * t
+            }
+
+            pub fn distance(&self, other: &Vector3) -> f64 {
+                (*other - *self).magnitude()
+            }
+
+            pub fn min(&self, other: &Vector3) -> Vector3 {
+                Vector3::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z))
+            }
+
+            pub fn max(&self, other: &Vector3) -> Vector3 {
+                Vector3::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z))
+            }
+
+            pub fn approx_eq(&self, other: &Vector3, epsilon: f64) -> bool {
+                (self.x - other.x).abs() <= epsilon &&
+                (self.y - other.y).abs() <= epsilon &&
+                (self.z - other.z).abs() <= epsilon
+            }
         }
 
         impl Add for Vector3 {
@@ -153,7 +461,99 @@ pub mod render_math {
                 Vector3 {
                     x: self.x * rhs.x,
                     y: self.y * rhs.y,
-                    z: self.y * rhs.y
+                    z: self.z * rhs.z
+                }
+            }
+        }
+
+        impl Div for Vector3 {
+            type Output = Vector3;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                Vector3 {
+                    x: self.x / rhs.x,
+                    y: self.y / rhs.y,
+                    z: self.z / rhs.z
+                }
+            }
+        }
+
+        impl Div<f64> for Vector3 {
+            type Output = Vector3;
+
+            fn div(self, rhs: f64) -> Self::Output {
+                Vector3 {
+                    x: self.x / rhs,
+                    y: self.y / rhs,
+                    z: self.z / rhs
+                }
+            }
+        }
+
+        impl Neg for Vector3 {
+            type Output = Vector3;
+
+            fn neg(self) -> Self::Output {
+                Vector3 {
+                    x: -self.x,
+                    y: -self.y,
+                    z: -self.z
+                }
+            }
+        }
+
+        impl AddAssign for Vector3 {
+            fn add_assign(&mut self, rhs: Self) {
+                self.x += rhs.x;
+                self.y += rhs.y;
+                self.z += rhs.z;
+            }
+        }
+
+        impl SubAssign for Vector3 {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.x -= rhs.x;
+                self.y -= rhs.y;
+                self.z -= rhs.z;
+            }
+        }
+
+        impl MulAssign<f64> for Vector3 {
+            fn mul_assign(&mut self, rhs: f64) {
+                self.x *= rhs;
+                self.y *= rhs;
+                self.z *= rhs;
+            }
+        }
+
+        impl DivAssign<f64> for Vector3 {
+            fn div_assign(&mut self, rhs: f64) {
+                self.x /= rhs;
+                self.y /= rhs;
+                self.z /= rhs;
+            }
+        }
+
+        impl Index<usize> for Vector3 {
+            type Output = f64;
+
+            fn index(&self, index: usize) -> &Self::Output {
+                match index {
+                    0 => &self.x,
+                    1 => &self.y,
+                    2 => &self.z,
+                    _ => panic!("Vector3 index {index} out of range."),
+                }
+            }
+        }
+
+        impl IndexMut<usize> for Vector3 {
+            fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+                match index {
+                    0 => &mut self.x,
+                    1 => &mut self.y,
+                    2 => &mut self.z,
+                    _ => panic!("Vector3 index {index} out of range."),
                 }
             }
         }
@@ -201,6 +601,55 @@ pub mod render_math {
             pub fn w(&self) -> f64 {
                 self.w
             }
+
+            pub fn mut_x(&mut self) -> &mut f64 {
+                &mut self.x
+            }
+            pub fn mut_y(&mut self) -> &mut f64 {
+                &mut self.y
+            }
+            pub fn mut_z(&mut self) -> &mut f64 {
+                &mut self.z
+            }
+            pub fn mut_w(&mut self) -> &mut f64 {
+                &mut self.w
+            }
+
+            pub fn dot(&self, other: &Vector4) -> f64 {
+                self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+            }
+
+            // Zero vector stays zero instead of turning into NaN
+            pub fn normalize(&self) -> Vector4 {
+                let length = self.magnitude();
+                if length == 0.0 {
+                    return *self;
+                }
+                *self / length
+            }
+
+            pub fn lerp(&self, other: &Vector4, t: f64) -> Vector4 {
+                *self + (*other - *self) * t
+            }
+
+            pub fn distance(&self, other: &Vector4) -> f64 {
+                (*other - *self).magnitude()
+            }
+
+            pub fn min(&self, other: &Vector4) -> Vector4 {
+                Vector4::new(self.x.min(other.x), self.y.min(other.y), self.z.min(other.z), self.w.min(other.w))
+            }
+
+            pub fn max(&self, other: &Vector4) -> Vector4 {
+                Vector4::new(self.x.max(other.x), self.y.max(other.y), self.z.max(other.z), self.w.max(other.w))
+            }
+
+            pub fn approx_eq(&self, other: &Vector4, epsilon: f64) -> bool {
+                (self.x - other.x).abs() <= epsilon &&
+                (self.y - other.y).abs() <= epsilon &&
+                (self.z - other.z).abs() <= epsilon &&
+                (self.w - other.w).abs() <= epsilon
+            }
         }
 
         impl Mul<&Mat4> for Vector4 {
@@ -255,442 +704,4696 @@ pub mod render_math {
             }
         }
 
-        impl Magnitude for Vector2i {
-            fn magnitude(&self) -> f64 {
-                f64::from(self.x * self.x + self.y * self.y).sqrt()
+        impl Mul<f64> for Vector4 {
+            type Output = Vector4;
+
+            fn mul(self, rhs: f64) -> Self::Output {
+                Vector4 {
+                    x: self.x * rhs,
+                    y: self.y * rhs,
+                    z: self.z * rhs,
+                    w: self.w * rhs
+                }
             }
         }
 
-        impl Magnitude for Vector3 {
-            fn magnitude(&self) -> f64 {
-                (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+        impl Div for Vector4 {
+            type Output = Vector4;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                Vector4 {
+                    x: self.x / rhs.x,
+                    y: self.y / rhs.y,
+                    z: self.z / rhs.z,
+                    w: self.w / rhs.w
+                }
             }
         }
 
-        impl Magnitude for Vector4 {
-            fn magnitude(&self) -> f64 {
-                (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt()
+        impl Div<f64> for Vector4 {
+            type Output = Vector4;
+
+            fn div(self, rhs: f64) -> Self::Output {
+                Vector4 {
+                    x: self.x / rhs,
+                    y: self.y / rhs,
+                    z: self.z / rhs,
+                    w: self.w / rhs
+                }
             }
         }
-    }
 
-    use crate::render_math::{matrix::Mat4, vector::{Vector3, Vector4}};
+        impl Neg for Vector4 {
+            type Output = Vector4;
 
+            fn neg(self) -> Self::Output {
+                Vector4 {
+                    x: -self.x,
+                    y: -self.y,
+                    z: -self.z,
+                    w: -self.w
+                }
+            }
+        }
+
+        impl AddAssign for Vector4 {
+            fn add_assign(&mut self, rhs: Self) {
+                self.x += rhs.x;
+                self.y += rhs.y;
+                self.z += rhs.z;
+                self.w += rhs.w;
+            }
+        }
+
+        impl SubAssign for Vector4 {
+            fn sub_assign(&mut self, rhs: Self) {
+                self.x -= rhs.x;
+                self.y -= rhs.y;
+                self.z -= rhs.z;
+                self.w -= rhs.w;
+            }
+        }
+
+        impl MulAssign<f64> for Vector4 {
+            fn mul_assign(&mut self, rhs: f64) {
+                self.x *= rhs;
+                self.y *= rhs;
+                self.z *= rhs;
+                self.w *= rhs;
+            }
+        }
+
+        impl DivAssign<f64> for Vector4 {
+            fn div_assign(&mut self, rhs: f64) {
+                self.x /= rhs;
+                self.y /= rhs;
+                self.z /= rhs;
+                self.w /= rhs;
+            }
+        }
+
+        impl Index<usize> for Vector4 {
+            type Output = f64;
+
+            fn index(&self, index: usize) -> &Self::Output {
+                match index {
+                    0 => &self.x,
+                    1 => &self.y,
+                    2 => &self.z,
+                    3 => &self.w,
+                    _ => panic!("Vector4 index {index} out of range."),
+                }
+            }
+        }
+
+        impl IndexMut<usize> for Vector4 {
+            fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+                match index {
+                    0 => &mut self.x,
+                    1 => &mut self.y,
+                    2 => &mut self.z,
+                    3 => &mut self.w,
+                    _ => panic!("Vector4 index {index} out of range."),
+                }
+            }
+        }
+
+        impl Magnitude for Vector2i {
+            fn magnitude(&self) -> f64 {
+                f64::from(self.x * self.x + self.y * self.y).sqrt()
+            }
+        }
+
+        impl Magnitude for Vector2 {
+            fn magnitude(&self) -> f64 {
+                (self.x * self.x + self.y * self.y).sqrt()
+            }
+        }
+
+        impl Magnitude for Vector3 {
+            fn magnitude(&self) -> f64 {
+                (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+            }
+        }
+
+        impl Magnitude for Vector4 {
+            fn magnitude(&self) -> f64 {
+                (self.x * self.x + self.y * self.y + self.z * self.z + self.w * self.w).sqrt()
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn vector3_mul_is_component_wise() {
+                let v = Vector3::new(1.0, 2.0, 3.0) * Vector3::new(4.0, 5.0, 6.0);
+                assert_eq!(v, Vector3::new(4.0, 10.0, 18.0));
+            }
+
+            #[test]
+            fn vector2i_mul_is_component_wise() {
+                let v = Vector2i::new(2, -3) * Vector2i::new(4, 5);
+                assert_eq!(v, Vector2i::new(8, -15));
+                assert_eq!(Vector2i::new(2, -3) * 3, Vector2i::new(6, -9));
+            }
+
+            #[test]
+            fn cross_follows_the_right_hand_rule() {
+                let x = Vector3::new(1.0, 0.0, 0.0);
+                let y = Vector3::new(0.0, 1.0, 0.0);
+                assert_eq!(x.cross(&y), Vector3::new(0.0, 0.0, 1.0));
+                assert_eq!(y.cross(&x), Vector3::new(0.0, 0.0, -1.0));
+                assert_eq!(x.cross(&x), Vector3::new(0.0, 0.0, 0.0));
+            }
+
+            #[test]
+            fn normalize_keeps_zero_vectors_zero() {
+                assert_eq!(Vector2::new(0.0, 0.0).normalize(), Vector2::new(0.0, 0.0));
+                assert_eq!(Vector3::new(0.0, 0.0, 0.0).normalize(), Vector3::new(0.0, 0.0, 0.0));
+                assert_eq!(Vector4::new(0.0, 0.0, 0.0, 0.0).normalize(), Vector4::new(0.0, 0.0, 0.0, 0.0));
+                assert_eq!(Vector3::new(0.0, 3.0, 4.0).normalize(), Vector3::new(0.0, 0.6, 0.8));
+            }
+
+            #[test]
+            fn index_reads_and_writes_components() {
+                let mut v = Vector4::new(1.0, 2.0, 3.0, 4.0);
+                assert_eq!([v[0], v[1], v[2], v[3]], [1.0, 2.0, 3.0, 4.0]);
+                v[2] = 7.0;
+                assert_eq!(v.z(), 7.0);
+
+                let mut v = Vector3::new(1.0, 2.0, 3.0);
+                v[0] = -1.0;
+                assert_eq!(v, Vector3::new(-1.0, 2.0, 3.0));
+                assert_eq!(Vector2::new(5.0, 6.0)[1], 6.0);
+            }
+
+            #[test]
+            #[should_panic]
+            fn index_out_of_range_panics() {
+                _ = Vector3::new(1.0, 2.0, 3.0)[3];
+            }
+
+            #[test]
+            fn approx_eq_uses_epsilon_per_component() {
+                let a = Vector3::new(1.0, 2.0, 3.0);
+                assert!(a.approx_eq(&Vector3::new(1.0 + 1e-10, 2.0, 3.0 - 1e-10), 1e-9));
+                assert!(!a.approx_eq(&Vector3::new(1.0, 2.0, 3.1), 1e-9));
+                assert!(Vector2::new(0.1 + 0.2, 0.0).approx_eq(&Vector2::new(0.3, 0.0), 1e-12));
+            }
+        }
+    }
+
+    use crate::render_math::{matrix::Mat4, quaternion::Quaternion, transform::Transform, vector::{Magnitude, Vector2, Vector3}};
+    use std::f64::consts::{PI, TAU};
+    use std::ops::Range;
+
+    #[derive(Debug, Clone)]
     pub struct Mesh {
         verts: Vec<Vector3>,
+        triangles: Vec<[usize; 3]>,
+
+        // Optional per-vertex attributes, either empty or as long as verts
+        normals: Vec<Vector3>,
+        uvs: Vec<Vector2>,
+        colors: Vec<[u8; 3]>,
+        // Group name and the index of its first triangle
+        groups: Vec<(String, usize)>,
 
         translation_matrix: Mat4,
         scale_matrix: Mat4,
         rotation_matrix: Mat4,
+        // translation * rotation * scale, composed again whenever one of them is set
+        model_matrix: Mat4,
     }
 
     impl Mesh {
         pub fn new() -> Self {
             Mesh {
                 verts: Vec::new(),
+                triangles: Vec::new(),
+
+                normals: Vec::new(),
+                uvs: Vec::new(),
+                colors: Vec::new(),
+                groups: Vec::new(),
 
                 translation_matrix: Mat4::identity(),
                 scale_matrix: Mat4::identity(),
-                rotation_matrix: Mat4::identity() 
+                rotation_matrix: Mat4::identity(),
+                model_matrix: Mat4::identity(),
             }
         }
 
         pub fn set_translation_matrix(&mut self, mat: Mat4) {
             self.translation_matrix = mat;
+            self.compose_model_matrix();
         }
 
         pub fn set_rotation_matrix(&mut self, mat: Mat4) {
             self.rotation_matrix = mat;
+            self.compose_model_matrix();
         }
 
         pub fn set_scale_matrix(&mut self, mat: Mat4) {
             self.scale_matrix = mat;
+            self.compose_model_matrix();
+        }
+
+        pub fn set_rotation(&mut self, rotation: Quaternion) {
+            self.set_rotation_matrix(rotation.to_mat4());
+        }
+
+        pub fn set_transform(&mut self, transform: &Transform) {
+            self.translation_matrix = transform.translation_matrix();
+            self.rotation_matrix = transform.rotation_matrix();
+            self.scale_matrix = transform.scale_matrix();
+            self.compose_model_matrix();
+        }
+
+        fn compose_model_matrix(&mut self) {
+            self.model_matrix = self.translation_matrix * self.rotation_matrix * self.scale_matrix;
+        }
+
+        pub fn model_matrix(&self) -> Mat4 {
+            self.model_matrix
         }
         
         pub fn add_vertex(&mut self, point: Vector3) {
             self.verts.push(point);
         }
 
-        pub fn get_transformed_verts(&self) -> Vec<Vector3> {
-            let mut v = Vec::new();
-
-            for vertice in self.verts.iter() {
-                v.push(
-                    Vector3::from(
-                        &(Vector4::from(vertice) * &self.scale_matrix * &self.rotation_matrix * &self.translation_matrix)
-                    )
-                );
+        pub fn add_triangle(&mut self, a: usize, b: usize, c: usize) -> Result<(), &'static str> {
+            if a >= self.verts.len() || b >= self.verts.len() || c >= self.verts.len() {
+                return Err("Triangle references a vertex that does not exist.");
             }
-
-            v
+            self.triangles.push([a, b, c]);
+            Ok(())
         }
-    }
-
-    pub mod matrix {
-        use crate::render_math::vector::Vector3;
-        use std::ops::Mul;
 
-        #[derive(Debug)]
-        pub struct Mat4 {
-            values: [[f64;4]; 4],
+        // Quads and other convex polygons are split into a triangle fan around the first index.
+        pub fn add_face(&mut self, indices: &[usize]) -> Result<(), &'static str> {
+            if indices.len() < 3 {
+                return Err("Face needs at least three vertices.");
+            }
+            if indices.iter().any(|i| *i >= self.verts.len()) {
+                return Err("Face references a vertex that does not exist.");
+            }
+            for i in 1..indices.len() - 1 {
+                self.triangles.push([indices[0], indices[i], indices[i + 1]]);
+            }
+            Ok(())
         }
 
-        impl Mat4 {
-            pub fn values(&self) -> [[f64;4]; 4] {
-                self.values
+        pub fn set_normals(&mut self, normals: Vec<Vector3>) -> Result<(), &'static str> {
+            if !normals.is_empty() && normals.len() != self.verts.len() {
+                return Err("Normal count does not match vertex count.");
             }
+            self.normals = normals;
+            Ok(())
+        }
 
-            pub fn mutable_values(&mut self) -> &mut [[f64;4]; 4] {
-                &mut self.values
+        pub fn set_uvs(&mut self, uvs: Vec<Vector2>) -> Result<(), &'static str> {
+            if !uvs.is_empty() && uvs.len() != self.verts.len() {
+                return Err("UV count does not match vertex count.");
             }
+            self.uvs = uvs;
+            Ok(())
+        }
 
-            pub fn new(values: [[f64;4]; 4]) -> Self {
-                Mat4 {
-                    values
-                }
+        pub fn set_colors(&mut self, colors: Vec<[u8; 3]>) -> Result<(), &'static str> {
+            if !colors.is_empty() && colors.len() != self.verts.len() {
+                return Err("Color count does not match vertex count.");
             }
+            self.colors = colors;
+            Ok(())
+        }
 
-            pub fn empty() -> Self {
-                Mat4 {
-                    values: [[0.0;4];4]
-                }
-            }
+        // Triangles added after this call belong to the group, until the next one begins
+        pub fn begin_group(&mut self, name: &str) {
+            self.groups.push((String::from(name), self.triangles.len()));
+        }
 
-            pub fn identity() -> Self {
-                Mat4 {
-                    values: [
-                        [1.0, 0.0, 0.0, 0.0],
-                        [0.0, 1.0, 0.0, 0.0],
-                        [0.0, 0.0, 1.0, 0.0],
-                        [0.0, 0.0, 0.0, 1.0]
-                    ]
-                }
-            }
+        pub fn verts(&self) -> &Vec<Vector3> {
+            &self.verts
+        }
 
-            pub fn scale(s: Vector3) -> Self {
-                Mat4 {
-                    values: [
-                        [s.x(), 0.0, 0.0, 0.0],
-                        [0.0, s.y(), 0.0, 0.0],
-                        [0.0, 0.0, s.z(), 0.0],
-                        [0.0, 0.0, 0.0, 1.0]
-                    ]
-                }
+        pub fn normals(&self) -> &Vec<Vector3> {
+            &self.normals
+        }
+
+        pub fn uvs(&self) -> &Vec<Vector2> {
+            &self.uvs
+        }
+
+        pub fn colors(&self) -> &Vec<[u8; 3]> {
+            &self.colors
+        }
+
+        // Group names with the range of triangles they cover
+        pub fn groups(&self) -> Vec<(&str, Range<usize>)> {
+            let mut groups = Vec::new();
+
+            for (i, (name, start)) in self.groups.iter().enumerate() {
+                let end = match self.groups.get(i + 1) {
+                    Some((_, next)) => *next,
+                    None => self.triangles.len(),
+                };
+                groups.push((name.as_str(), *start..end));
             }
 
-            pub fn translation(t: Vector3) -> Self {
-                Mat4 {
-                    values: [
-                        [1.0, 0.0, 0.0, t.x()],
-                        [0.0, 1.0, 0.0, t.y()],
-                        [0.0, 0.0, 1.0, t.z()],
-                        [0.0, 0.0, 0.0, 1.0]
-                    ]
+            groups
+        }
+
+        pub fn triangles(&self) -> &Vec<[usize; 3]> {
+            &self.triangles
+        }
+
+        // Every edge shared by several triangles is only returned once, lower index first
+        pub fn edges(&self) -> Vec<[usize; 2]> {
+            let mut seen = std::collections::HashSet::new();
+            let mut edges = Vec::new();
+
+            for t in self.triangles.iter() {
+                for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                    let edge = [a.min(b), a.max(b)];
+                    if seen.insert(edge) {
+                        edges.push(edge);
+                    }
                 }
             }
 
-            pub fn euler_rotation(euler: Vector3) -> Self {
-                let x_mat = [
-                    [1.0, 0.0, 0.0, 0.0],
-                    [0.0, euler.x().cos(), -euler.x().sin(), 0.0],
-                    [0.0, euler.x().sin(), euler.x().cos(), 0.0],
-                    [0.0, 0.0, 0.0, 1.0]
-                ];
+            edges
+        }
 
-                let y_mat = [
-                    [euler.y().cos(), 0.0, euler.y().sin(), 0.0],
-                    [0.0, 1.0, 0.0, 0.0],
-                    [-euler.y().sin(), 0.0, euler.y().cos(), 0.0],
-                    [0.0, 0.0, 0.0, 1.0]
-                ];
+        pub fn get_transformed_verts(&self) -> Vec<Vector3> {
+            let mut v = Vec::with_capacity(self.verts.len());
+            self.transform_verts_into(&mut v);
+            v
+        }
 
-                let z_mat = [
-                    [euler.z().cos(), -euler.z().sin(), 0.0, 0.0],
-                    [euler.z().sin(), euler.z().cos(), 0.0, 0.0],
-                    [0.0, 0.0, 1.0, 0.0],
-                    [0.0, 0.0, 0.0, 1.0]
-                ];
+        // Reuses the buffer's allocation, meant to be called every frame
+        pub fn transform_verts_into(&self, out: &mut Vec<Vector3>) {
+            let model = self.model_matrix();
 
-                let x_mat = Mat4::new(x_mat);
-                let y_mat = Mat4::new(y_mat);
-                let z_mat = Mat4::new(z_mat);
+            out.clear();
+            out.extend(self.verts.iter().map(|v| model.transform_point(v)));
+        }
 
-                x_mat * y_mat * z_mat
+        pub fn get_transformed_faces(&self) -> Vec<[Vector3; 3]> {
+            let verts = self.get_transformed_verts();
+
+            self.triangles.iter()
+                .map(|t| [verts[t[0]], verts[t[1]], verts[t[2]]])
+                .collect()
+        }
+    }
+
+    // Primitive generators. All of them are centered on the origin with Y up and come with normals and UVs.
+    // Resolutions below the minimum that still produces a closed shape are raised to it.
+    impl Mesh {
+        pub fn cube(size: f64) -> Self {
+            let mut mesh = Mesh::new();
+            let half = size / 2.0;
+
+            let faces = [
+                (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0)),
+                (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0)),
+                (Vector3::new(0.0, 1.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+                (Vector3::new(0.0, -1.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+                (Vector3::new(0.0, 0.0, 1.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+                (Vector3::new(0.0, 0.0, -1.0), Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+            ];
+            for (normal, u_axis, v_axis) in faces {
+                mesh.add_grid(1, 1, |u, v| {
+                    ((normal + u_axis * (2.0 * u - 1.0) + v_axis * (2.0 * v - 1.0)) * half, normal)
+                });
             }
+
+            mesh
         }
 
-        impl Mul for Mat4 {
-            type Output = Self;
+        pub fn plane(width: f64, depth: f64, x_segments: usize, z_segments: usize) -> Self {
+            let mut mesh = Mesh::new();
 
-            fn mul(self, rhs: Self) -> Self::Output {
-                let mut result: [[f64;4];4] = [[0.0;4];4];
+            mesh.add_grid(x_segments.max(1), z_segments.max(1), |u, v| {
+                (Vector3::new((u - 0.5) * width, 0.0, (v - 0.5) * depth), Vector3::new(0.0, 1.0, 0.0))
+            });
 
-                for row in 0..4 {
-                    for column in 0..4 {
-                        result[row][column] = 
-                            self.values[row][0] * rhs.values[0][column] +
-                            self.values[row][1] * rhs.values[1][column] +
-                            self.values[row][2] * rhs.values[2][column] +
-                            self.values[row][3] * rhs.values[3][column];
-                    }
+            mesh
+        }
+
+        pub fn uv_sphere(radius: f64, segments: usize, rings: usize) -> Self {
+            let mut mesh = Mesh::new();
+
+            mesh.add_grid(segments.max(3), rings.max(2), |u, v| {
+                let n = spherical(u * TAU, v * PI);
+                (n * radius, n)
+            });
+
+            mesh
+        }
+
+        // Subdivided icosahedron, every subdivision splits each triangle into four
+        pub fn icosphere(radius: f64, subdivisions: usize) -> Self {
+            let t = (1.0 + 5.0_f64.sqrt()) / 2.0;
+            let mut points = vec![
+                Vector3::new(-1.0, t, 0.0), Vector3::new(1.0, t, 0.0), Vector3::new(-1.0, -t, 0.0), Vector3::new(1.0, -t, 0.0),
+                Vector3::new(0.0, -1.0, t), Vector3::new(0.0, 1.0, t), Vector3::new(0.0, -1.0, -t), Vector3::new(0.0, 1.0, -t),
+                Vector3::new(t, 0.0, -1.0), Vector3::new(t, 0.0, 1.0), Vector3::new(-t, 0.0, -1.0), Vector3::new(-t, 0.0, 1.0),
+            ];
+            let mut faces: Vec<[usize; 3]> = vec![
+                [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+                [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+                [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+                [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+            ];
+
+            for _ in 0..subdivisions {
+                let mut midpoints = std::collections::HashMap::new();
+                let mut midpoint = |a: usize, b: usize, points: &mut Vec<Vector3>| -> usize {
+                    *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                        points.push((points[a] + points[b]) / 2.0);
+                        points.len() - 1
+                    })
+                };
+
+                let mut next = Vec::with_capacity(faces.len() * 4);
+                for [a, b, c] in faces {
+                    let ab = midpoint(a, b, &mut points);
+                    let bc = midpoint(b, c, &mut points);
+                    let ca = midpoint(c, a, &mut points);
+                    next.extend([[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
                 }
+                faces = next;
+            }
 
-                Mat4::new(result)
+            let mut mesh = Mesh::new();
+            for p in points {
+                let n = p.normalize();
+                mesh.verts.push(n * radius);
+                mesh.normals.push(n);
+                mesh.uvs.push(Vector2::new(0.5 + n.z().atan2(n.x()) / TAU, n.y().clamp(-1.0, 1.0).acos() / PI));
             }
+            for [a, b, c] in faces {
+                mesh.add_oriented_triangle(a, b, c);
+            }
+
+            mesh
         }
-    }
-}
 
+        pub fn cylinder(radius: f64, height: f64, segments: usize) -> Self {
+            let mut mesh = Mesh::new();
+            let segments = segments.max(3);
 
-pub mod render {
-    pub struct Screen<T: Default> {
-        pixels: Vec<T>,
-        width: u32,
-        height: u32,
-    }
+            mesh.add_grid(segments, 1, |u, v| {
+                let n = Vector3::new((u * TAU).cos(), 0.0, (u * TAU).sin());
+                (n * radius + Vector3::new(0.0, (v - 0.5) * height, 0.0), n)
+            });
+            mesh.add_disc(height / 2.0, radius, segments, true);
+            mesh.add_disc(-height / 2.0, radius, segments, false);
 
-    impl<T: Default> Screen<T> {
-        pub fn new(x: u32, y: u32) -> Self {
-            let mut screen = Screen {
-                pixels: Vec::new(),
-                width: x,
-                height: y,
-            };
-            screen.clear();
-            screen
+            mesh
         }
 
-        pub fn resize(&mut self, x: u32, y: u32) {
-            self.width = x;
-            self.height = y;
-            self.clear();
+        // Apex on top, base at the bottom
+        pub fn cone(radius: f64, height: f64, segments: usize) -> Self {
+            let mut mesh = Mesh::new();
+            let segments = segments.max(3);
+
+            mesh.add_grid(segments, 1, |u, v| {
+                let (cos, sin) = ((u * TAU).cos(), (u * TAU).sin());
+                let n = Vector3::new(height * cos, radius, height * sin).normalize();
+                let r = radius * (1.0 - v);
+                (Vector3::new(r * cos, (v - 0.5) * height, r * sin), n)
+            });
+            mesh.add_disc(-height / 2.0, radius, segments, false);
+
+            mesh
         }
 
-        pub fn clear(&mut self) {
-            let total = self.height * self.width;
-            self.pixels.clear();
-            for _ in 0..total {
-                self.pixels.push(T::default());
+        // Lies in the XZ plane, major radius is measured to the center of the tube
+        pub fn torus(major_radius: f64, minor_radius: f64, major_segments: usize, minor_segments: usize) -> Self {
+            let mut mesh = Mesh::new();
+
+            mesh.add_grid(major_segments.max(3), minor_segments.max(3), |u, v| {
+                let (major, minor) = (u * TAU, v * TAU);
+                let n = Vector3::new(minor.cos() * major.cos(), minor.sin(), minor.cos() * major.sin());
+                let center = Vector3::new(major.cos(), 0.0, major.sin()) * major_radius;
+                (center + n * minor_radius, n)
+            });
+
+            mesh
+        }
+
+        // Height is the length of the cylindrical part, the caps add a radius on each end.
+        // Rings are per hemisphere.
+        pub fn capsule(radius: f64, height: f64, segments: usize, rings: usize) -> Self {
+            let mut mesh = Mesh::new();
+            let rings = rings.max(1);
+            let total = height + 2.0 * radius;
+
+            // One extra row splits the sphere at the equator so both halves can be offset
+            mesh.add_grid(segments.max(3), 2 * rings + 1, |u, v| {
+                let row = (v * (2 * rings + 1) as f64).round() as usize;
+                let (polar, offset) = if row <= rings {
+                    (row as f64 / rings as f64 * PI / 2.0, height / 2.0)
+                }
+                else {
+                    ((row - 1) as f64 / rings as f64 * PI / 2.0, -height / 2.0)
+                };
+
+                let n = spherical(u * TAU, polar);
+                let p = n * radius + Vector3::new(0.0, offset, 0.0);
+                (p, n)
+            });
+
+            // Stretch the V coordinate along the height instead of the row index
+            for (uv, p) in mesh.uvs.iter_mut().zip(mesh.verts.iter()) {
+                *uv.mut_y() = 0.5 - p.y() / total;
             }
+
+            mesh
         }
-        
-        pub fn set_pixel(&mut self, x: u32, y: u32, pixel: T) -> Result<(), &'static str> {
-            let i: usize = match (y * self.width + x).try_into() {
-                Ok(v) => v,
-                Err(_) => return Err("Desired pixel index does not fit into usize.")
-            };
-            if let Some(px) = self.pixels.get_mut(i) {
-                *px = pixel;
+
+        // Extrudes a closed 2D outline in the XY plane along Z, centered on the origin, with both ends capped.
+        // The outline may be concave but must not intersect itself. Consecutive duplicate points are ignored.
+        pub fn extrude(outline: &[Vector2], depth: f64) -> Result<Self, &'static str> {
+            let points = clean_outline(outline);
+            if points.len() < 3 {
+                return Err("Outline needs at least three distinct points.");
+            }
+            if signed_area(&points).abs() < 1e-12 {
+                return Err("Outline has no area.");
+            }
+            let cap = triangulate(&points).ok_or("Outline could not be triangulated.")?;
+
+            let mut mesh = Mesh::new();
+            let min = points.iter().fold(points[0], |m, p| m.min(p));
+            let size = points.iter().fold(points[0], |m, p| m.max(p)) - min;
+
+            for (z, facing) in [(depth / 2.0, 1.0), (-depth / 2.0, -1.0)] {
+                let start = mesh.verts.len();
+                for p in points.iter() {
+                    mesh.verts.push(Vector3::new(p.x(), p.y(), z));
+                    mesh.normals.push(Vector3::new(0.0, 0.0, facing));
+                    mesh.uvs.push((*p - min) / size);
+                }
+                for t in cap.iter() {
+                    mesh.add_oriented_triangle(start + t[0], start + t[1], start + t[2]);
+                }
+            }
+
+            // Side walls are flat shaded, every segment gets its own four vertices
+            let n = points.len();
+            for i in 0..n {
+                let (a, b) = (points[i], points[(i + 1) % n]);
+                let edge = b - a;
+                // Points are counter-clockwise, so the right hand side of every edge faces outwards
+                let normal = Vector3::new(edge.y(), -edge.x(), 0.0).normalize();
+
+                let start = mesh.verts.len();
+                for (p, z, u, v) in [
+                    (a, depth / 2.0, i, 0.0),
+                    (b, depth / 2.0, i + 1, 0.0),
+                    (b, -depth / 2.0, i + 1, 1.0),
+                    (a, -depth / 2.0, i, 1.0),
+                ] {
+                    mesh.verts.push(Vector3::new(p.x(), p.y(), z));
+                    mesh.normals.push(normal);
+                    mesh.uvs.push(Vector2::new(u as f64 / n as f64, v));
+                }
+                mesh.add_oriented_triangle(start, start + 1, start + 2);
+                mesh.add_oriented_triangle(start, start + 2, start + 3);
+            }
+
+            Ok(mesh)
+        }
+
+        // Revolves a profile around the Y axis. Profile x is the distance from the axis, y the height.
+        // Normals point outwards when the profile runs from bottom to top. Ends are left open unless
+        // the profile starts or ends on the axis.
+        pub fn lathe(profile: &[Vector2], segments: usize) -> Result<Self, &'static str> {
+            if profile.len() < 2 {
+                return Err("Profile needs at least two points.");
+            }
+            if profile.iter().any(|p| p.x() < 0.0) {
+                return Err("Profile must not cross the axis.");
+            }
+
+            // Smooth normals from the profile's tangent, one sided at the ends
+            let last = profile.len() - 1;
+            let normals: Vec<Vector2> = (0..profile.len())
+                .map(|i| {
+                    let tangent = profile[(i + 1).min(last)] - profile[i.saturating_sub(1)];
+                    Vector2::new(tangent.y(), -tangent.x()).normalize()
+                })
+                .collect();
+
+            let mut mesh = Mesh::new();
+            mesh.add_grid(segments.max(3), last, |u, v| {
+                let row = (v * last as f64).round() as usize;
+                let (p, n) = (profile[row], normals[row]);
+                let (cos, sin) = ((u * TAU).cos(), (u * TAU).sin());
+
+                (Vector3::new(p.x() * cos, p.y(), p.x() * sin), Vector3::new(n.x() * cos, n.y(), n.x() * sin))
+            });
+
+            Ok(mesh)
+        }
+
+        // Builds a (columns + 1) x (rows + 1) vertex grid, point(u, v) returns position and normal for u and v in [0; 1]
+        fn add_grid(&mut self, columns: usize, rows: usize, point: impl Fn(f64, f64) -> (Vector3, Vector3)) {
+            let start = self.verts.len();
+
+            for row in 0..=rows {
+                for column in 0..=columns {
+                    let (u, v) = (column as f64 / columns as f64, row as f64 / rows as f64);
+                    let (p, n) = point(u, v);
+                    self.verts.push(p);
+                    self.normals.push(n);
+                    self.uvs.push(Vector2::new(u, v));
+                }
+            }
+
+            for row in 0..rows {
+                for column in 0..columns {
+                    let a = start + row * (columns + 1) + column;
+                    let b = a + 1;
+                    let c = a + columns + 1;
+                    let d = c + 1;
+                    self.add_oriented_triangle(a, b, d);
+                    self.add_oriented_triangle(a, d, c);
+                }
+            }
+        }
+
+        // Flat cap at height y facing up or down
+        fn add_disc(&mut self, y: f64, radius: f64, segments: usize, up: bool) {
+            let normal = Vector3::new(0.0, if up { 1.0 } else { -1.0 }, 0.0);
+            let center = self.verts.len();
+
+            self.verts.push(Vector3::new(0.0, y, 0.0));
+            self.normals.push(normal);
+            self.uvs.push(Vector2::new(0.5, 0.5));
+            for i in 0..segments {
+                let angle = i as f64 / segments as f64 * TAU;
+                self.verts.push(Vector3::new(angle.cos() * radius, y, angle.sin() * radius));
+                self.normals.push(normal);
+                self.uvs.push(Vector2::new(0.5 + angle.cos() / 2.0, 0.5 + angle.sin() / 2.0));
+            }
+
+            for i in 0..segments {
+                self.add_oriented_triangle(center, center + 1 + i, center + 1 + (i + 1) % segments);
+            }
+        }
+
+        // Skips degenerate triangles (sphere poles, cone apex) and winds the rest counter-clockwise
+        // when seen from the side their vertex normals point to
+        fn add_oriented_triangle(&mut self, a: usize, b: usize, c: usize) {
+            let face = (self.verts[b] - self.verts[a]).cross(&(self.verts[c] - self.verts[a]));
+            if face.magnitude() < 1e-12 {
+                return;
+            }
+
+            let normal = self.normals[a] + self.normals[b] + self.normals[c];
+            if face.dot(&normal) < 0.0 {
+                self.triangles.push([a, c, b]);
+            }
+            else {
+                self.triangles.push([a, b, c]);
+            }
+        }
+    }
+
+    // Drops repeated points (including a closing point equal to the first one) and makes the outline counter-clockwise
+    fn clean_outline(outline: &[Vector2]) -> Vec<Vector2> {
+        let mut points: Vec<Vector2> = Vec::new();
+        for p in outline {
+            if points.last().is_none_or(|last| !last.approx_eq(p, 1e-9)) {
+                points.push(*p);
+            }
+        }
+        while points.len() > 1 && points[0].approx_eq(&points[points.len() - 1], 1e-9) {
+            points.pop();
+        }
+
+        if signed_area(&points) < 0.0 {
+            points.reverse();
+        }
+        points
+    }
+
+    fn signed_area(points: &[Vector2]) -> f64 {
+        let n = points.len();
+        (0..n).map(|i| points[i].perp_dot(&points[(i + 1) % n])).sum::<f64>() / 2.0
+    }
+
+    // Ear clipping for a counter-clockwise simple polygon. None if no ear can be found, e.g. when it intersects itself.
+    fn triangulate(points: &[Vector2]) -> Option<Vec<[usize; 3]>> {
+        let mut remaining: Vec<usize> = (0..points.len()).collect();
+        let mut triangles = Vec::new();
+
+        while remaining.len() > 3 {
+            let n = remaining.len();
+            let ear = (0..n).find(|i| {
+                let (a, b, c) = (remaining[(i + n - 1) % n], remaining[*i], remaining[(i + 1) % n]);
+                let (pa, pb, pc) = (points[a], points[b], points[c]);
+
+                // Reflex or collinear corners can't be ears
+                if (pb - pa).perp_dot(&(pc - pb)) <= 0.0 {
+                    return false;
+                }
+
+                !remaining.iter()
+                    .filter(|r| **r != a && **r != b && **r != c)
+                    .any(|r| {
+                        let p = points[*r];
+                        (pb - pa).perp_dot(&(p - pa)) >= 0.0 &&
+                        (pc - pb).perp_dot(&(p - pb)) >= 0.0 &&
+                        (pa - pc).perp_dot(&(p - pc)) >= 0.0
+                    })
+            })?;
+
+            triangles.push([remaining[(ear + n - 1) % n], remaining[ear], remaining[(ear + 1) % n]]);
+            remaining.remove(ear);
+        }
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+
+        Some(triangles)
+    }
+
+    // Unit vector for an azimuth around Y and a polar angle measured from +Y
+    fn spherical(azimuth: f64, polar: f64) -> Vector3 {
+        Vector3::new(polar.sin() * azimuth.cos(), polar.cos(), polar.sin() * azimuth.sin())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::render_math::vector::Vector4;
+
+        // Every triangle is wound counter-clockwise when seen from the side its vertex normals point to
+        fn assert_wound_along_normals(mesh: &Mesh) {
+            for &[a, b, c] in mesh.triangles() {
+                let v = mesh.verts();
+                let face = (v[b] - v[a]).cross(&(v[c] - v[a]));
+                let normal = mesh.normals()[a] + mesh.normals()[b] + mesh.normals()[c];
+                assert!(face.dot(&normal) > 0.0, "triangle {:?} is wound against its normals", [a, b, c]);
+            }
+        }
+
+        // For convex meshes around the origin
+        fn assert_faces_point_outwards(mesh: &Mesh) {
+            for &[a, b, c] in mesh.triangles() {
+                let v = mesh.verts();
+                let face = (v[b] - v[a]).cross(&(v[c] - v[a]));
+                let centroid = (v[a] + v[b] + v[c]) / 3.0;
+                assert!(face.dot(&centroid) > 0.0, "triangle {:?} points inwards", [a, b, c]);
+            }
+        }
+
+        fn assert_counts(mesh: &Mesh, verts: usize, triangles: usize) {
+            assert_eq!((mesh.verts().len(), mesh.triangles().len()), (verts, triangles));
+            assert_eq!(mesh.normals().len(), verts);
+            assert_eq!(mesh.uvs().len(), verts);
+        }
+
+        #[test]
+        fn primitive_counts() {
+            assert_counts(&Mesh::cube(2.0), 24, 12);
+            assert_counts(&Mesh::plane(2.0, 2.0, 3, 2), 12, 12);
+            // The triangles touching the poles or the apex collapse and are skipped
+            assert_counts(&Mesh::uv_sphere(1.0, 8, 4), 45, 48);
+            assert_counts(&Mesh::icosphere(1.0, 0), 12, 20);
+            assert_counts(&Mesh::icosphere(1.0, 1), 42, 80);
+            assert_counts(&Mesh::cylinder(1.0, 2.0, 8), 36, 32);
+            assert_counts(&Mesh::cone(1.0, 2.0, 8), 27, 16);
+            assert_counts(&Mesh::torus(2.0, 0.5, 8, 6), 63, 96);
+            assert_counts(&Mesh::capsule(1.0, 2.0, 8, 2), 54, 64);
+        }
+
+        #[test]
+        fn primitives_are_wound_along_their_normals() {
+            for mesh in [
+                Mesh::cube(2.0),
+                Mesh::plane(2.0, 2.0, 3, 2),
+                Mesh::uv_sphere(1.0, 8, 4),
+                Mesh::icosphere(1.0, 2),
+                Mesh::cylinder(1.0, 2.0, 8),
+                Mesh::cone(1.0, 2.0, 8),
+                Mesh::torus(2.0, 0.5, 8, 6),
+                Mesh::capsule(1.0, 2.0, 8, 2),
+            ] {
+                assert_wound_along_normals(&mesh);
+            }
+        }
+
+        #[test]
+        fn closed_convex_primitives_face_outwards() {
+            for mesh in [
+                Mesh::cube(2.0),
+                Mesh::uv_sphere(1.0, 8, 4),
+                Mesh::icosphere(1.0, 2),
+                Mesh::cylinder(1.0, 2.0, 8),
+                Mesh::cone(1.0, 2.0, 8),
+                Mesh::capsule(1.0, 2.0, 8, 2),
+            ] {
+                assert_faces_point_outwards(&mesh);
+            }
+        }
+
+        #[test]
+        fn sphere_vertices_lie_on_the_radius() {
+            for mesh in [Mesh::uv_sphere(3.0, 8, 4), Mesh::icosphere(3.0, 2)] {
+                assert!(mesh.verts().iter().all(|v| (v.magnitude() - 3.0).abs() < 1e-9));
+            }
+        }
+
+        fn square() -> Vec<Vector2> {
+            vec![Vector2::new(-1.0, -1.0), Vector2::new(1.0, -1.0), Vector2::new(1.0, 1.0), Vector2::new(-1.0, 1.0)]
+        }
+
+        #[test]
+        fn extrude_counts_and_winding() {
+            let prism = Mesh::extrude(&square(), 2.0).unwrap();
+            // Two caps of four vertices and two triangles, four walls of four vertices and two triangles
+            assert_counts(&prism, 24, 12);
+            assert_wound_along_normals(&prism);
+            assert_faces_point_outwards(&prism);
+
+            // Clockwise and with a repeated closing point, the result is the same
+            let mut clockwise = square();
+            clockwise.reverse();
+            clockwise.push(clockwise[0]);
+            let reversed = Mesh::extrude(&clockwise, 2.0).unwrap();
+            assert_counts(&reversed, 24, 12);
+            assert_faces_point_outwards(&reversed);
+        }
+
+        #[test]
+        fn extrude_concave_outline() {
+            let l_shape = [
+                Vector2::new(0.0, 0.0), Vector2::new(2.0, 0.0), Vector2::new(2.0, 1.0),
+                Vector2::new(1.0, 1.0), Vector2::new(1.0, 2.0), Vector2::new(0.0, 2.0),
+            ];
+            let mesh = Mesh::extrude(&l_shape, 1.0).unwrap();
+            assert_counts(&mesh, 36, 20);
+            assert_wound_along_normals(&mesh);
+        }
+
+        #[test]
+        fn extrude_rejects_degenerate_outlines() {
+            let line = [Vector2::new(0.0, 0.0), Vector2::new(1.0, 1.0), Vector2::new(2.0, 2.0)];
+            assert_eq!(Mesh::extrude(&line, 1.0).unwrap_err(), "Outline has no area.");
+            let repeated = [Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(1.0, 0.0), Vector2::new(0.0, 0.0)];
+            assert!(Mesh::extrude(&repeated, 1.0).is_err());
+        }
+
+        #[test]
+        fn lathe_counts_and_winding() {
+            let tube = Mesh::lathe(&[Vector2::new(1.0, -1.0), Vector2::new(1.0, 1.0)], 8).unwrap();
+            assert_counts(&tube, 18, 16);
+            assert_wound_along_normals(&tube);
+            assert_faces_point_outwards(&tube);
+            for (v, n) in tube.verts().iter().zip(tube.normals()) {
+                assert!(n.approx_eq(&Vector3::new(v.x(), 0.0, v.z()), 1e-9));
+            }
+
+            // Closed at both ends on the axis, the triangles there collapse
+            let diamond = Mesh::lathe(&[Vector2::new(0.0, -1.0), Vector2::new(1.0, 0.0), Vector2::new(0.0, 1.0)], 8).unwrap();
+            assert_counts(&diamond, 27, 16);
+            assert_wound_along_normals(&diamond);
+            assert_faces_point_outwards(&diamond);
+        }
+
+        #[test]
+        fn lathe_rejects_invalid_profiles() {
+            assert!(Mesh::lathe(&[Vector2::new(1.0, 0.0)], 8).is_err());
+            assert!(Mesh::lathe(&[Vector2::new(1.0, 0.0), Vector2::new(-1.0, 1.0)], 8).is_err());
+        }
+
+        fn transformed_cube() -> (Mesh, Mat4, Mat4, Mat4) {
+            let t = Mat4::translation(Vector3::new(1.0, -2.0, 3.0));
+            let r = Quaternion::from_axis_angle(Vector3::new(1.0, 1.0, 0.0), 0.7).to_mat4();
+            let s = Mat4::scale(Vector3::new(2.0, 0.5, 1.5));
+
+            let mut mesh = Mesh::cube(1.0);
+            mesh.set_translation_matrix(t);
+            mesh.set_rotation_matrix(r);
+            mesh.set_scale_matrix(s);
+            (mesh, t, r, s)
+        }
+
+        #[test]
+        fn setters_recompose_model_matrix() {
+            let (mut mesh, t, r, s) = transformed_cube();
+            assert_eq!(mesh.model_matrix(), t * r * s);
+
+            let moved = Mat4::translation(Vector3::new(-4.0, 0.0, 0.0));
+            mesh.set_translation_matrix(moved);
+            assert_eq!(mesh.model_matrix(), moved * r * s);
+
+            let turned = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), 1.2);
+            mesh.set_rotation(turned);
+            assert_eq!(mesh.model_matrix(), moved * turned.to_mat4() * s);
+
+            let flat = Mat4::scale(Vector3::new(1.0, 0.0, 1.0));
+            mesh.set_scale_matrix(flat);
+            assert_eq!(mesh.model_matrix(), moved * turned.to_mat4() * flat);
+
+            let transform = Transform::new(Vector3::new(0.0, 1.0, 0.0), Quaternion::identity(), Vector3::new(3.0, 3.0, 3.0));
+            mesh.set_transform(&transform);
+            assert_eq!(mesh.model_matrix(), transform.to_mat4());
+        }
+
+        #[test]
+        fn transform_verts_into_applies_scale_then_rotation_then_translation() {
+            let (mesh, t, r, s) = transformed_cube();
+            let mut out = vec![Vector3::new(9.0, 9.0, 9.0)];
+            mesh.transform_verts_into(&mut out);
+
+            assert_eq!(out.len(), mesh.verts().len());
+            for (v, transformed) in mesh.verts().iter().zip(out.iter()) {
+                let expected = Vector3::from(&(Vector4::from(v) * &s * &r * &t));
+                assert!(transformed.approx_eq(&expected, 1e-12));
+            }
+            assert_eq!(mesh.get_transformed_verts(), out);
+        }
+    }
+
+    pub mod matrix {
+        use crate::render_math::vector::{Magnitude, Vector3};
+        use std::ops::Mul;
+
+        // Cross products of unit vectors shorter than this are treated as parallel
+        const PARALLEL_EPSILON: f64 = 1e-12;
+
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct Mat4 {
+            values: [[f64;4]; 4],
+        }
+
+        impl Mat4 {
+            pub fn values(&self) -> [[f64;4]; 4] {
+                self.values
+            }
+
+            pub fn mutable_values(&mut self) -> &mut [[f64;4]; 4] {
+                &mut self.values
+            }
+
+            pub fn new(values: [[f64;4]; 4]) -> Self {
+                Mat4 {
+                    values
+                }
+            }
+
+            pub fn empty() -> Self {
+                Mat4 {
+                    values: [[0.0;4];4]
+                }
+            }
+
+            pub fn identity() -> Self {
+                Mat4 {
+                    values: [
+                        [1.0, 0.0, 0.0, 0.0],
+                        [0.0, 1.0, 0.0, 0.0],
+                        [0.0, 0.0, 1.0, 0.0],
+                        [0.0, 0.0, 0.0, 1.0]
+                    ]
+                }
+            }
+
+            pub fn scale(s: Vector3) -> Self {
+                Mat4 {
+                    values: [
+                        [s.x(), 0.0, 0.0, 0.0],
+                        [0.0, s.y(), 0.0, 0.0],
+                        [0.0, 0.0, s.z(), 0.0],
+                        [0.0, 0.0, 0.0, 1.0]
+                    ]
+                }
+            }
+
+            pub fn translation(t: Vector3) -> Self {
+                Mat4 {
+                    values: [
+                        [1.0, 0.0, 0.0, t.x()],
+                        [0.0, 1.0, 0.0, t.y()],
+                        [0.0, 0.0, 1.0, t.z()],
+                        [0.0, 0.0, 0.0, 1.0]
+                    ]
+                }
+            }
+
+            pub fn euler_rotation(euler: Vector3) -> Self {
+                let x_mat = [
+                    [1.0, 0.0, 0.0, 0.0],
+                    [0.0, euler.x().cos(), -euler.x().sin(), 0.0],
+                    [0.0, euler.x().sin(), euler.x().cos(), 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ];
+
+                let y_mat = [
+                    [euler.y().cos(), 0.0, euler.y().sin(), 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [-euler.y().sin(), 0.0, euler.y().cos(), 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ];
+
+                let z_mat = [
+                    [euler.z().cos(), -euler.z().sin(), 0.0, 0.0],
+                    [euler.z().sin(), euler.z().cos(), 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ];
+
+                let x_mat = Mat4::new(x_mat);
+                let y_mat = Mat4::new(y_mat);
+                let z_mat = Mat4::new(z_mat);
+
+                x_mat * y_mat * z_mat
+            }
+
+            // Right handed rotation of angle radians around axis, which does not have to be normalized
+            pub fn rotation_axis_angle(axis: Vector3, angle: f64) -> Self {
+                let length = axis.magnitude();
+                if length == 0.0 {
+                    return Mat4::identity();
+                }
+
+                let (x, y, z) = (axis.x() / length, axis.y() / length, axis.z() / length);
+                let (sin, cos) = angle.sin_cos();
+                let t = 1.0 - cos;
+
+                Mat4::new([
+                    [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+                    [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+                    [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            }
+
+            // Right handed view matrix with the eye looking at target, the same as gluLookAt.
+            // None when eye and target coincide or up is parallel to the view direction.
+            pub fn look_at(eye: Vector3, target: Vector3, up: Vector3) -> Option<Self> {
+                let forward = (target - eye).normalize();
+                let side = forward.cross(&up.normalize());
+                if side.magnitude() < PARALLEL_EPSILON {
+                    return None;
+                }
+                let side = side.normalize();
+                let up = side.cross(&forward);
+
+                Some(Mat4::new([
+                    [side.x(), side.y(), side.z(), -side.dot(&eye)],
+                    [up.x(), up.y(), up.z(), -up.dot(&eye)],
+                    [-forward.x(), -forward.y(), -forward.z(), forward.dot(&eye)],
+                    [0.0, 0.0, 0.0, 1.0]
+                ]))
+            }
+
+            // OpenGL style projection onto a -1..1 depth range. fov is vertical, in radians.
+            pub fn perspective(fov: f64, aspect: f64, near: f64, far: f64) -> Self {
+                let f = 1.0 / (fov / 2.0).tan();
+                let depth = near - far;
+
+                Mat4::new([
+                    [f / aspect, 0.0, 0.0, 0.0],
+                    [0.0, f, 0.0, 0.0],
+                    [0.0, 0.0, (far + near) / depth, 2.0 * far * near / depth],
+                    [0.0, 0.0, -1.0, 0.0]
+                ])
+            }
+
+            // OpenGL style, the same as glOrtho
+            pub fn orthographic(left: f64, right: f64, bottom: f64, top: f64, near: f64, far: f64) -> Self {
+                let width = right - left;
+                let height = top - bottom;
+                let depth = far - near;
+
+                Mat4::new([
+                    [2.0 / width, 0.0, 0.0, -(right + left) / width],
+                    [0.0, 2.0 / height, 0.0, -(top + bottom) / height],
+                    [0.0, 0.0, -2.0 / depth, -(far + near) / depth],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            }
+
+            // xy is how much x moves per unit of y, and so on
+            pub fn shear(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
+                Mat4::new([
+                    [1.0, xy, xz, 0.0],
+                    [yx, 1.0, yz, 0.0],
+                    [zx, zy, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            }
+
+            // Mirrors across the plane through the origin with the given normal
+            pub fn reflection(normal: Vector3) -> Self {
+                let n = normal.normalize();
+                let (x, y, z) = (n.x(), n.y(), n.z());
+
+                Mat4::new([
+                    [1.0 - 2.0 * x * x, -2.0 * x * y, -2.0 * x * z, 0.0],
+                    [-2.0 * x * y, 1.0 - 2.0 * y * y, -2.0 * y * z, 0.0],
+                    [-2.0 * x * z, -2.0 * y * z, 1.0 - 2.0 * z * z, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            }
+
+            pub fn transpose(&self) -> Self {
+                let mut result: [[f64;4];4] = [[0.0;4];4];
+
+                for (row, values) in self.values.iter().enumerate() {
+                    for (column, value) in values.iter().enumerate() {
+                        result[column][row] = *value;
+                    }
+                }
+
+                Mat4::new(result)
+            }
+
+            // Both determinant and inverse are built from the 2x2 sub-determinants of the upper and lower halves
+            fn sub_determinants(&self) -> ([f64; 6], [f64; 6]) {
+                let a = &self.values;
+
+                let s = [
+                    a[0][0] * a[1][1] - a[1][0] * a[0][1],
+                    a[0][0] * a[1][2] - a[1][0] * a[0][2],
+                    a[0][0] * a[1][3] - a[1][0] * a[0][3],
+                    a[0][1] * a[1][2] - a[1][1] * a[0][2],
+                    a[0][1] * a[1][3] - a[1][1] * a[0][3],
+                    a[0][2] * a[1][3] - a[1][2] * a[0][3],
+                ];
+                let c = [
+                    a[2][0] * a[3][1] - a[3][0] * a[2][1],
+                    a[2][0] * a[3][2] - a[3][0] * a[2][2],
+                    a[2][0] * a[3][3] - a[3][0] * a[2][3],
+                    a[2][1] * a[3][2] - a[3][1] * a[2][2],
+                    a[2][1] * a[3][3] - a[3][1] * a[2][3],
+                    a[2][2] * a[3][3] - a[3][2] * a[2][3],
+                ];
+
+                (s, c)
+            }
+
+            pub fn determinant(&self) -> f64 {
+                let (s, c) = self.sub_determinants();
+                Mat4::determinant_of(&s, &c)
+            }
+
+            fn determinant_of(s: &[f64; 6], c: &[f64; 6]) -> f64 {
+                s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+            }
+
+            // None if the matrix is singular
+            // None for singular matrices. Any non-zero determinant is accepted since its size depends on the
+            // scale of the matrix, e.g. a uniform scale by 5e-5 has a determinant of about 1e-17.
+            pub fn inverse(&self) -> Option<Self> {
+                let (s, c) = self.sub_determinants();
+                let det = Mat4::determinant_of(&s, &c);
+                if det == 0.0 || !det.is_finite() {
+                    return None;
+                }
+
+                let a = &self.values;
+                let inv_det = 1.0 / det;
+                let result = [
+                    [
+                        ( a[1][1] * c[5] - a[1][2] * c[4] + a[1][3] * c[3]) * inv_det,
+                        (-a[0][1] * c[5] + a[0][2] * c[4] - a[0][3] * c[3]) * inv_det,
+                        ( a[3][1] * s[5] - a[3][2] * s[4] + a[3][3] * s[3]) * inv_det,
+                        (-a[2][1] * s[5] + a[2][2] * s[4] - a[2][3] * s[3]) * inv_det,
+                    ],
+                    [
+                        (-a[1][0] * c[5] + a[1][2] * c[2] - a[1][3] * c[1]) * inv_det,
+                        ( a[0][0] * c[5] - a[0][2] * c[2] + a[0][3] * c[1]) * inv_det,
+                        (-a[3][0] * s[5] + a[3][2] * s[2] - a[3][3] * s[1]) * inv_det,
+                        ( a[2][0] * s[5] - a[2][2] * s[2] + a[2][3] * s[1]) * inv_det,
+                    ],
+                    [
+                        ( a[1][0] * c[4] - a[1][1] * c[2] + a[1][3] * c[0]) * inv_det,
+                        (-a[0][0] * c[4] + a[0][1] * c[2] - a[0][3] * c[0]) * inv_det,
+                        ( a[3][0] * s[4] - a[3][1] * s[2] + a[3][3] * s[0]) * inv_det,
+                        (-a[2][0] * s[4] + a[2][1] * s[2] - a[2][3] * s[0]) * inv_det,
+                    ],
+                    [
+                        (-a[1][0] * c[3] + a[1][1] * c[1] - a[1][2] * c[0]) * inv_det,
+                        ( a[0][0] * c[3] - a[0][1] * c[1] + a[0][2] * c[0]) * inv_det,
+                        (-a[3][0] * s[3] + a[3][1] * s[1] - a[3][2] * s[0]) * inv_det,
+                        ( a[2][0] * s[3] - a[2][1] * s[1] + a[2][2] * s[0]) * inv_det,
+                    ],
+                ];
+
+                if result.iter().flatten().any(|v| !v.is_finite()) {
+                    return None;
+                }
+                Some(Mat4::new(result))
+            }
+
+            // Same as Vector4::from(point) * self, without copying the values for every component
+            pub fn transform_point(&self, point: &Vector3) -> Vector3 {
+                let m = &self.values;
+                Vector3::new(
+                    m[0][0] * point.x() + m[0][1] * point.y() + m[0][2] * point.z() + m[0][3],
+                    m[1][0] * point.x() + m[1][1] * point.y() + m[1][2] * point.z() + m[1][3],
+                    m[2][0] * point.x() + m[2][1] * point.y() + m[2][2] * point.z() + m[2][3],
+                )
+            }
+
+            // Transforms normals correctly under non-uniform scale
+            pub fn inverse_transpose(&self) -> Option<Self> {
+                self.inverse().map(|m| m.transpose())
+            }
+        }
+
+        impl Mul for Mat4 {
+            type Output = Self;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                let mut result: [[f64;4];4] = [[0.0;4];4];
+
+                for row in 0..4 {
+                    for column in 0..4 {
+                        result[row][column] = 
+                            self.values[row][0] * rhs.values[0][column] +
+                            self.values[row][1] * rhs.values[1][column] +
+                            self.values[row][2] * rhs.values[2][column] +
+                            self.values[row][3] * rhs.values[3][column];
+                    }
+                }
+
+                Mat4::new(result)
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use crate::render_math::quaternion::Quaternion;
+
+            fn assert_close(actual: Mat4, expected: [[f64; 4]; 4]) {
+                for (a, e) in actual.values().iter().flatten().zip(expected.iter().flatten()) {
+                    assert!((a - e).abs() < 1e-6, "{:?} != {:?}", actual.values(), expected);
+                }
+            }
+
+            #[test]
+            fn look_at_matches_glu_look_at() {
+                // gluLookAt(1, 2, 3, 0, 0, 0, 0, 1, 0)
+                let m = Mat4::look_at(Vector3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)).unwrap();
+                assert_close(m, [
+                    [0.948683, 0.0, -0.316228, 0.0],
+                    [-0.169031, 0.845154, -0.507093, 0.0],
+                    [0.267261, 0.534522, 0.801784, -3.741657],
+                    [0.0, 0.0, 0.0, 1.0]
+                ]);
+            }
+
+            #[test]
+            fn look_at_rejects_degenerate_input() {
+                let up = Vector3::new(0.0, 1.0, 0.0);
+                assert_eq!(Mat4::look_at(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 5.0, 0.0), up), None);
+                assert_eq!(Mat4::look_at(Vector3::new(1.0, 1.0, 1.0), Vector3::new(1.0, 1.0, 1.0), up), None);
+            }
+
+            #[test]
+            fn perspective_matches_glu_perspective() {
+                // gluPerspective(90, 2, 1, 10)
+                let m = Mat4::perspective(std::f64::consts::FRAC_PI_2, 2.0, 1.0, 10.0);
+                assert_close(m, [
+                    [0.5, 0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, -11.0 / 9.0, -20.0 / 9.0],
+                    [0.0, 0.0, -1.0, 0.0]
+                ]);
+            }
+
+            #[test]
+            fn orthographic_matches_gl_ortho() {
+                // glOrtho(0, 4, 0, 2, -1, 1)
+                let m = Mat4::orthographic(0.0, 4.0, 0.0, 2.0, -1.0, 1.0);
+                assert_close(m, [
+                    [0.5, 0.0, 0.0, -1.0],
+                    [0.0, 1.0, 0.0, -1.0],
+                    [0.0, 0.0, -1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ]);
+            }
+
+            #[test]
+            fn rotation_axis_angle_matches_quaternion() {
+                let axis = Vector3::new(1.0, 2.0, -0.5);
+                for angle in [-2.0, 0.3, 0.8, 3.0] {
+                    let expected = Quaternion::from_axis_angle(axis, angle).to_mat4();
+                    assert_close(Mat4::rotation_axis_angle(axis, angle), expected.values());
+                }
+                assert_eq!(Mat4::rotation_axis_angle(Vector3::new(0.0, 0.0, 0.0), 1.0), Mat4::identity());
+            }
+
+            #[test]
+            fn shear_moves_along_other_axes() {
+                let m = Mat4::shear(2.0, 0.0, 0.0, 0.5, 0.0, 0.0);
+                assert_eq!(m.transform_point(&Vector3::new(1.0, 3.0, 4.0)), Vector3::new(7.0, 5.0, 4.0));
+            }
+
+            #[test]
+            fn reflection_mirrors_across_plane() {
+                let m = Mat4::reflection(Vector3::new(1.0, 1.0, 0.0));
+                assert!(m.transform_point(&Vector3::new(1.0, 0.0, 0.0)).approx_eq(&Vector3::new(0.0, -1.0, 0.0), 1e-12));
+                assert!((m.determinant() + 1.0).abs() < 1e-12);
+                assert_close(m * m, Mat4::identity().values());
+            }
+
+            fn general() -> Mat4 {
+                Mat4::new([
+                    [2.0, 0.0, 1.0, 3.0],
+                    [1.0, 3.0, 0.0, -1.0],
+                    [0.0, 1.0, 4.0, 2.0],
+                    [1.0, 0.0, 2.0, 5.0],
+                ])
+            }
+
+            #[test]
+            fn transpose_swaps_rows_and_columns() {
+                let t = general().transpose();
+                assert_eq!(t.values()[0], [2.0, 1.0, 0.0, 1.0]);
+                assert_eq!(t.values()[3], [3.0, -1.0, 2.0, 5.0]);
+                assert_eq!(t.transpose(), general());
+            }
+
+            #[test]
+            fn determinant_of_general_matrix() {
+                assert!((general().determinant() - 62.0).abs() < 1e-9);
+                assert!((general().transpose().determinant() - 62.0).abs() < 1e-9);
+                assert_eq!(Mat4::identity().determinant(), 1.0);
+            }
+
+            #[test]
+            fn inverse_of_general_matrix() {
+                let m = general();
+                let inverse = m.inverse().unwrap();
+                assert_close(m * inverse, Mat4::identity().values());
+                assert_close(inverse * m, Mat4::identity().values());
+                assert!((inverse.determinant() - 1.0 / 62.0).abs() < 1e-12);
+            }
+
+            #[test]
+            fn inverse_of_singular_matrix_is_none() {
+                let mut values = general().values();
+                values[3] = [3.0, 3.0, 1.0, 2.0]; // sum of the first two rows
+                assert_eq!(Mat4::new(values).inverse(), None);
+                assert_eq!(Mat4::scale(Vector3::new(1.0, 0.0, 1.0)).inverse(), None);
+            }
+
+            #[test]
+            fn inverse_does_not_depend_on_scale() {
+                let m = Mat4::scale(Vector3::new(5e-5, 5e-5, 5e-5));
+                let inverse = m.inverse().unwrap();
+                assert_close(m * inverse, Mat4::identity().values());
+                assert_close(inverse, Mat4::scale(Vector3::new(2e4, 2e4, 2e4)).values());
+            }
+
+            #[test]
+            fn inverse_transpose_is_transposed_inverse() {
+                let m = general();
+                assert_close(m.inverse_transpose().unwrap(), m.inverse().unwrap().transpose().values());
+                assert_close(m.inverse_transpose().unwrap() * m.transpose(), Mat4::identity().values());
+            }
+        }
+    }
+
+    pub mod quaternion {
+        use crate::render_math::{matrix::Mat4, vector::{Magnitude, Vector3}};
+        use std::ops::Mul;
+
+        // Unit quaternions represent rotations. Multiplication composes them the same way as matrices do:
+        // (a * b).to_mat4() == a.to_mat4() * b.to_mat4()
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct Quaternion {
+            w: f64,
+            x: f64,
+            y: f64,
+            z: f64,
+        }
+
+        impl Quaternion {
+            pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+                Quaternion {
+                    w,
+                    x,
+                    y,
+                    z,
+                }
+            }
+
+            pub fn identity() -> Self {
+                Quaternion::new(1.0, 0.0, 0.0, 0.0)
+            }
+
+            // Angle in radians, the axis does not have to be normalized
+            pub fn from_axis_angle(axis: Vector3, angle: f64) -> Self {
+                let length = axis.magnitude();
+                if length == 0.0 {
+                    return Quaternion::identity();
+                }
+
+                let s = (angle / 2.0).sin() / length;
+                Quaternion::new((angle / 2.0).cos(), axis.x() * s, axis.y() * s, axis.z() * s)
+            }
+
+            // Same rotation as Mat4::euler_rotation
+            pub fn from_euler(euler: Vector3) -> Self {
+                let x = Quaternion::from_axis_angle(Vector3::new(1.0, 0.0, 0.0), euler.x());
+                let y = Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), euler.y());
+                let z = Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), euler.z());
+
+                x * y * z
+            }
+
+            // Only the rotation part (upper 3x3) is read, it must not contain scale
+            pub fn from_mat4(mat: &Mat4) -> Self {
+                let m = mat.values();
+                let trace = m[0][0] + m[1][1] + m[2][2];
+
+                let q = if trace > 0.0 {
+                    let s = (trace + 1.0).sqrt() * 2.0;
+                    Quaternion::new(0.25 * s, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s)
+                }
+                else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+                    let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+                    Quaternion::new((m[2][1] - m[1][2]) / s, 0.25 * s, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s)
+                }
+                else if m[1][1] > m[2][2] {
+                    let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+                    Quaternion::new((m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, 0.25 * s, (m[1][2] + m[2][1]) / s)
+                }
+                else {
+                    let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+                    Quaternion::new((m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, 0.25 * s)
+                };
+
+                q.normalize()
+            }
+
+            pub fn w(&self) -> f64 {
+                self.w
+            }
+            pub fn x(&self) -> f64 {
+                self.x
+            }
+            pub fn y(&self) -> f64 {
+                self.y
+            }
+            pub fn z(&self) -> f64 {
+                self.z
+            }
+
+            pub fn dot(&self, other: &Quaternion) -> f64 {
+                self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+            }
+
+            pub fn normalize(&self) -> Self {
+                let length = self.magnitude();
+                if length == 0.0 {
+                    return Quaternion::identity();
+                }
+
+                Quaternion::new(self.w / length, self.x / length, self.y / length, self.z / length)
+            }
+
+            pub fn conjugate(&self) -> Self {
+                Quaternion::new(self.w, -self.x, -self.y, -self.z)
+            }
+
+            pub fn inverse(&self) -> Option<Self> {
+                let length_squared = self.dot(self);
+                if length_squared == 0.0 {
+                    return None;
+                }
+
+                let c = self.conjugate();
+                Some(Quaternion::new(c.w / length_squared, c.x / length_squared, c.y / length_squared, c.z / length_squared))
+            }
+
+            // Spherical interpolation along the shortest arc, t in [0; 1]
+            pub fn slerp(&self, other: &Quaternion, t: f64) -> Self {
+                let mut end = *other;
+                let mut cos_theta = self.dot(other);
+                if cos_theta < 0.0 {
+                    end = Quaternion::new(-end.w, -end.x, -end.y, -end.z);
+                    cos_theta = -cos_theta;
+                }
+
+                // Nearly identical rotations, sin(theta) would be close to 0
+                if cos_theta > 0.9995 {
+                    return Quaternion::new(
+                        self.w + (end.w - self.w) * t,
+                        self.x + (end.x - self.x) * t,
+                        self.y + (end.y - self.y) * t,
+                        self.z + (end.z - self.z) * t,
+                    ).normalize();
+                }
+
+                let theta = cos_theta.acos();
+                let sin_theta = theta.sin();
+                let a = ((1.0 - t) * theta).sin() / sin_theta;
+                let b = (t * theta).sin() / sin_theta;
+
+                Quaternion::new(
+                    self.w * a + end.w * b,
+                    self.x * a + end.x * b,
+                    self.y * a + end.y * b,
+                    self.z * a + end.z * b,
+                )
+            }
+
+            pub fn to_mat4(&self) -> Mat4 {
+                let q = self.normalize();
+                let (w, x, y, z) = (q.w, q.x, q.y, q.z);
+
+                Mat4::new([
+                    [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - w * z), 2.0 * (x * z + w * y), 0.0],
+                    [2.0 * (x * y + w * z), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - w * x), 0.0],
+                    [2.0 * (x * z - w * y), 2.0 * (y * z + w * x), 1.0 - 2.0 * (x * x + y * y), 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            }
+
+            pub fn rotate(&self, v: Vector3) -> Vector3 {
+                let q = self.normalize();
+                let p = q * Quaternion::new(0.0, v.x(), v.y(), v.z()) * q.conjugate();
+
+                Vector3::new(p.x, p.y, p.z)
+            }
+        }
+
+        impl Mul for Quaternion {
+            type Output = Quaternion;
+
+            fn mul(self, rhs: Self) -> Self::Output {
+                Quaternion {
+                    w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+                    x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+                    y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+                    z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+                }
+            }
+        }
+
+        impl Magnitude for Quaternion {
+            fn magnitude(&self) -> f64 {
+                self.dot(self).sqrt()
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+            fn assert_mat_close(a: Mat4, b: Mat4) {
+                for (x, y) in a.values().iter().flatten().zip(b.values().iter().flatten()) {
+                    assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a.values(), b.values());
+                }
+            }
+
+            // q and -q are the same rotation
+            fn assert_same_rotation(a: Quaternion, b: Quaternion) {
+                assert!((a.dot(&b).abs() - 1.0).abs() < 1e-9, "{a:?} != {b:?}");
+            }
+
+            fn rotations() -> Vec<Quaternion> {
+                vec![
+                    Quaternion::identity(),
+                    Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 3.0), 1.1),
+                    Quaternion::from_axis_angle(Vector3::new(-0.5, 0.2, 0.9), 2.9),
+                    // Half turns have a zero w, from_mat4 has to pick another component to divide by
+                    Quaternion::from_axis_angle(Vector3::new(1.0, 0.0, 0.0), PI),
+                    Quaternion::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), PI),
+                    Quaternion::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), PI),
+                ]
+            }
+
+            #[test]
+            fn from_euler_matches_euler_rotation() {
+                for euler in [Vector3::new(0.3, -1.2, 2.5), Vector3::new(FRAC_PI_2, 0.0, 0.0), Vector3::new(-2.0, 0.7, -0.1)] {
+                    assert_mat_close(Quaternion::from_euler(euler).to_mat4(), Mat4::euler_rotation(euler));
+                }
+            }
+
+            #[test]
+            fn from_mat4_round_trips() {
+                for q in rotations() {
+                    let back = Quaternion::from_mat4(&q.to_mat4());
+                    assert_same_rotation(back, q);
+                    assert_mat_close(back.to_mat4(), q.to_mat4());
+                }
+            }
+
+            #[test]
+            fn rotate_matches_matrix() {
+                let v = Vector3::new(0.4, -2.0, 1.5);
+                for q in rotations() {
+                    assert!(q.rotate(v).approx_eq(&q.to_mat4().transform_point(&v), 1e-9));
+                }
+            }
+
+            #[test]
+            fn multiplication_composes_like_matrices() {
+                let (a, b) = (rotations()[1], rotations()[2]);
+                assert_mat_close((a * b).to_mat4(), a.to_mat4() * b.to_mat4());
+            }
+
+            #[test]
+            fn slerp_midpoint_is_half_the_rotation() {
+                let z = Vector3::new(0.0, 0.0, 1.0);
+                let quarter = Quaternion::from_axis_angle(z, FRAC_PI_2);
+                let expected = Quaternion::from_axis_angle(z, FRAC_PI_4);
+
+                assert_same_rotation(Quaternion::identity().slerp(&quarter, 0.5), expected);
+                // -quarter is the same rotation, the shortest arc is still taken
+                let negated = Quaternion::new(-quarter.w(), -quarter.x(), -quarter.y(), -quarter.z());
+                assert_same_rotation(Quaternion::identity().slerp(&negated, 0.5), expected);
+                assert_same_rotation(Quaternion::identity().slerp(&quarter, 0.0), Quaternion::identity());
+                assert_same_rotation(Quaternion::identity().slerp(&quarter, 1.0), quarter);
+            }
+        }
+    }
+
+    pub mod transform {
+        use crate::render_math::{matrix::Mat4, quaternion::Quaternion, vector::{Magnitude, Vector3}};
+
+        // Scales smaller than this can't be decomposed, the rotation would be undefined
+        const MIN_SCALE: f64 = 1e-12;
+
+        // Translation, rotation and scale, composed as T * R * S like the matrices of a Mesh
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub struct Transform {
+            translation: Vector3,
+            rotation: Quaternion,
+            scale: Vector3,
+        }
+
+        impl Transform {
+            pub fn new(translation: Vector3, rotation: Quaternion, scale: Vector3) -> Self {
+                Transform {
+                    translation,
+                    rotation,
+                    scale,
+                }
+            }
+
+            pub fn identity() -> Self {
+                Transform::new(Vector3::new(0.0, 0.0, 0.0), Quaternion::identity(), Vector3::new(1.0, 1.0, 1.0))
+            }
+
+            pub fn translation(&self) -> Vector3 {
+                self.translation
+            }
+            pub fn rotation(&self) -> Quaternion {
+                self.rotation
+            }
+            pub fn scale(&self) -> Vector3 {
+                self.scale
+            }
+
+            pub fn set_translation(&mut self, translation: Vector3) {
+                self.translation = translation;
+            }
+            pub fn set_rotation(&mut self, rotation: Quaternion) {
+                self.rotation = rotation;
+            }
+            pub fn set_scale(&mut self, scale: Vector3) {
+                self.scale = scale;
+            }
+
+            pub fn translation_matrix(&self) -> Mat4 {
+                Mat4::translation(self.translation)
+            }
+            pub fn rotation_matrix(&self) -> Mat4 {
+                self.rotation.normalize().to_mat4()
+            }
+            pub fn scale_matrix(&self) -> Mat4 {
+                Mat4::scale(self.scale)
+            }
+
+            pub fn to_mat4(&self) -> Mat4 {
+                self.translation_matrix() * self.rotation_matrix() * self.scale_matrix()
+            }
+
+            // Splits an affine matrix back into its parts. None for projections and matrices with a zero scale.
+            // Shear can't be represented and is lost. A mirroring matrix gets a negative x scale.
+            pub fn from_mat4(mat: &Mat4) -> Option<Self> {
+                let m = mat.values();
+                if m[3] != [0.0, 0.0, 0.0, 1.0] {
+                    return None;
+                }
+
+                let translation = Vector3::new(m[0][3], m[1][3], m[2][3]);
+
+                // The columns of the upper 3x3 are the scaled axes of the rotation
+                let axes: Vec<Vector3> = (0..3).map(|c| Vector3::new(m[0][c], m[1][c], m[2][c])).collect();
+                let mut scale = Vector3::new(axes[0].magnitude(), axes[1].magnitude(), axes[2].magnitude());
+                if scale.x() < MIN_SCALE || scale.y() < MIN_SCALE || scale.z() < MIN_SCALE {
+                    return None;
+                }
+                if axes[0].cross(&axes[1]).dot(&axes[2]) < 0.0 {
+                    *scale.mut_x() = -scale.x();
+                }
+
+                let mut rotation = Mat4::identity();
+                for (c, axis) in axes.iter().enumerate() {
+                    let axis = *axis / scale[c];
+                    for r in 0..3 {
+                        rotation.mutable_values()[r][c] = axis[r];
+                    }
+                }
+
+                Some(Transform::new(translation, Quaternion::from_mat4(&rotation), scale))
+            }
+
+            // Translation and scale are interpolated linearly, rotation along the shortest arc
+            pub fn lerp(&self, other: &Transform, t: f64) -> Self {
+                Transform::new(
+                    self.translation.lerp(&other.translation, t),
+                    self.rotation.slerp(&other.rotation, t),
+                    self.scale.lerp(&other.scale, t)
+                )
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            use std::f64::consts::FRAC_PI_2;
+
+            fn assert_mat_close(a: Mat4, b: Mat4) {
+                for (x, y) in a.values().iter().flatten().zip(b.values().iter().flatten()) {
+                    assert!((x - y).abs() < 1e-9, "{:?} != {:?}", a.values(), b.values());
+                }
+            }
+
+            #[test]
+            fn from_mat4_round_trips() {
+                let rotation = Quaternion::from_axis_angle(Vector3::new(0.3, -1.0, 0.5), 2.2);
+                let transform = Transform::new(Vector3::new(1.0, -2.0, 3.0), rotation, Vector3::new(2.0, 0.5, 3.0));
+
+                let split = Transform::from_mat4(&transform.to_mat4()).unwrap();
+                assert!(split.translation().approx_eq(&transform.translation(), 1e-9));
+                assert!(split.scale().approx_eq(&transform.scale(), 1e-9));
+                assert!((split.rotation().dot(&rotation).abs() - 1.0).abs() < 1e-9);
+                assert_mat_close(split.to_mat4(), transform.to_mat4());
+            }
+
+            #[test]
+            fn from_mat4_round_trips_negative_scale() {
+                let mirrored = Transform::new(Vector3::new(0.0, 1.0, 0.0), Quaternion::identity(), Vector3::new(-2.0, 1.0, 1.0));
+                let split = Transform::from_mat4(&mirrored.to_mat4()).unwrap();
+                assert!(split.scale().approx_eq(&Vector3::new(-2.0, 1.0, 1.0), 1e-9));
+                assert_mat_close(split.to_mat4(), mirrored.to_mat4());
+
+                // Mirrored along another axis the sign moves to x, the rotation makes up for it
+                let rotation = Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 0.0), 0.9);
+                let mirrored = Transform::new(Vector3::new(0.0, 0.0, 0.0), rotation, Vector3::new(1.0, -3.0, 2.0));
+                let split = Transform::from_mat4(&mirrored.to_mat4()).unwrap();
+                assert!(split.scale().x() < 0.0);
+                assert_mat_close(split.to_mat4(), mirrored.to_mat4());
+            }
+
+            #[test]
+            fn from_mat4_rejects_projections_and_zero_scale() {
+                assert_eq!(Transform::from_mat4(&Mat4::perspective(FRAC_PI_2, 1.0, 1.0, 10.0)), None);
+                assert_eq!(Transform::from_mat4(&Mat4::scale(Vector3::new(1.0, 0.0, 1.0))), None);
+            }
+
+            #[test]
+            fn lerp_interpolates_each_part() {
+                let z = Vector3::new(0.0, 0.0, 1.0);
+                let a = Transform::new(Vector3::new(0.0, 0.0, 0.0), Quaternion::identity(), Vector3::new(1.0, 1.0, 1.0));
+                let b = Transform::new(Vector3::new(2.0, 4.0, -2.0), Quaternion::from_axis_angle(z, FRAC_PI_2), Vector3::new(3.0, 1.0, 2.0));
+
+                let half = a.lerp(&b, 0.5);
+                assert!(half.translation().approx_eq(&Vector3::new(1.0, 2.0, -1.0), 1e-12));
+                assert!(half.scale().approx_eq(&Vector3::new(2.0, 1.0, 1.5), 1e-12));
+                assert_mat_close(half.rotation_matrix(), Quaternion::from_axis_angle(z, FRAC_PI_2 / 2.0).to_mat4());
+
+                assert_mat_close(a.lerp(&b, 0.0).to_mat4(), a.to_mat4());
+                assert_mat_close(a.lerp(&b, 1.0).to_mat4(), b.to_mat4());
+            }
+        }
+    }
+
+    pub mod camera {
+        use crate::render_math::{matrix::Mat4, vector::{Vector2, Vector3, Vector4}};
+
+        // Terminal cells are roughly twice as tall as they are wide
+        pub const CELL_ASPECT: f64 = 2.0;
+
+        // Right handed camera looking down its local -Z axis with Y up.
+        // Rotation is in euler angles (radians), applied the same way as Mat4::euler_rotation.
+        #[derive(Debug, Copy, Clone)]
+        pub struct Camera {
+            position: Vector3,
+            rotation: Vector3,
+
+            fov: f64,
+            near: f64,
+            far: f64,
+            aspect: f64,
+        }
+
+        impl Camera {
+            pub fn new(position: Vector3, rotation: Vector3, fov: f64, near: f64, far: f64, aspect: f64) -> Self {
+                Camera {
+                    position,
+                    rotation,
+
+                    fov,
+                    near,
+                    far,
+                    aspect,
+                }
+            }
+
+            // 60° vertical field of view, aspect ratio fitted to a terminal of w x h cells
+            pub fn for_terminal(w: u32, h: u32) -> Self {
+                Camera::new(
+                    Vector3::new(0.0, 0.0, 0.0),
+                    Vector3::new(0.0, 0.0, 0.0),
+                    std::f64::consts::FRAC_PI_3,
+                    0.1,
+                    100.0,
+                    Camera::terminal_aspect(w, h)
+                )
+            }
+
+            pub fn terminal_aspect(w: u32, h: u32) -> f64 {
+                f64::from(w) / (f64::from(h) * CELL_ASPECT)
+            }
+
+            pub fn position(&self) -> Vector3 {
+                self.position
+            }
+            pub fn rotation(&self) -> Vector3 {
+                self.rotation
+            }
+            pub fn fov(&self) -> f64 {
+                self.fov
+            }
+            pub fn near(&self) -> f64 {
+                self.near
+            }
+            pub fn far(&self) -> f64 {
+                self.far
+            }
+            pub fn aspect(&self) -> f64 {
+                self.aspect
+            }
+
+            pub fn set_position(&mut self, position: Vector3) {
+                self.position = position;
+            }
+            pub fn set_rotation(&mut self, rotation: Vector3) {
+                self.rotation = rotation;
+            }
+            pub fn set_fov(&mut self, fov: f64) {
+                self.fov = fov;
+            }
+            pub fn set_clip_planes(&mut self, near: f64, far: f64) {
+                self.near = near;
+                self.far = far;
+            }
+            pub fn set_aspect(&mut self, aspect: f64) {
+                self.aspect = aspect;
+            }
+
+            // Inverse of the camera's world transform: R^T * T(-position)
+            pub fn view_matrix(&self) -> Mat4 {
+                // The inverse of a pure rotation is its transpose
+                Mat4::euler_rotation(self.rotation).transpose() * Mat4::translation(-self.position)
+            }
+
+            pub fn projection_matrix(&self) -> Mat4 {
+                Mat4::perspective(self.fov, self.aspect, self.near, self.far)
+            }
+
+            // Maps normalized device coordinates onto a screen of w x h cells, Y pointing down
+            pub fn viewport_matrix(w: u32, h: u32) -> Mat4 {
+                let half_w = f64::from(w) / 2.0;
+                let half_h = f64::from(h) / 2.0;
+
+                Mat4::new([
+                    [half_w, 0.0, 0.0, half_w],
+                    [0.0, -half_h, 0.0, half_h],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0]
+                ])
+            }
+
+            pub fn screen_matrix(&self, w: u32, h: u32) -> Mat4 {
+                Camera::viewport_matrix(w, h) * self.projection_matrix() * self.view_matrix()
+            }
+
+            // Returns the screen position of a world point and its distance along the view direction.
+            // Points outside of the near and far planes are discarded.
+            pub fn project(&self, screen_matrix: &Mat4, point: &Vector3) -> Option<(Vector2, f64)> {
+                let clip = Vector4::from(point) * screen_matrix;
+                if clip.w() < self.near || clip.w() > self.far {
+                    return None;
+                }
+
+                Some((Vector2::new(clip.x() / clip.w(), clip.y() / clip.w()), clip.w()))
+            }
+        }
+    }
+
+    pub mod scene {
+        use crate::render_math::{Mesh, matrix::Mat4, transform::Transform};
+
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub struct NodeId(usize);
+
+        #[derive(Debug, Clone)]
+        pub struct Node {
+            name: String,
+            // Relative to the parent node, or to the world for root nodes
+            local_matrix: Mat4,
+            mesh: Option<Mesh>,
+
+            parent: Option<NodeId>,
+            children: Vec<NodeId>,
+        }
+
+        impl Node {
+            pub fn name(&self) -> &str {
+                &self.name
+            }
+            pub fn local_matrix(&self) -> Mat4 {
+                self.local_matrix
+            }
+            pub fn mesh(&self) -> Option<&Mesh> {
+                self.mesh.as_ref()
+            }
+            pub fn parent(&self) -> Option<NodeId> {
+                self.parent
+            }
+            pub fn children(&self) -> &Vec<NodeId> {
+                &self.children
+            }
+
+            pub fn mut_mesh(&mut self) -> Option<&mut Mesh> {
+                self.mesh.as_mut()
+            }
+
+            pub fn set_local_matrix(&mut self, mat: Mat4) {
+                self.local_matrix = mat;
+            }
+            pub fn set_local_transform(&mut self, transform: &Transform) {
+                self.local_matrix = transform.to_mat4();
+            }
+            pub fn set_mesh(&mut self, mesh: Option<Mesh>) {
+                self.mesh = mesh;
+            }
+        }
+
+        // Nodes are stored flat and refer to each other by NodeId, so they can be reached
+        // and animated directly while still forming a hierarchy
+        #[derive(Debug, Clone, Default)]
+        pub struct Scene {
+            nodes: Vec<Node>,
+            roots: Vec<NodeId>,
+        }
+
+        impl Scene {
+            pub fn new() -> Self {
+                Scene {
+                    nodes: Vec::new(),
+                    roots: Vec::new(),
+                }
+            }
+
+            pub fn add_node(&mut self, name: &str, parent: Option<NodeId>, local_matrix: Mat4, mesh: Option<Mesh>) -> Result<NodeId, &'static str> {
+                if parent.is_some_and(|p| p.0 >= self.nodes.len()) {
+                    return Err("Parent node does not exist");
+                }
+
+                let id = NodeId(self.nodes.len());
+                self.nodes.push(Node {
+                    name: String::from(name),
+                    local_matrix,
+                    mesh,
+
+                    parent,
+                    children: Vec::new(),
+                });
+
+                match parent {
+                    Some(p) => self.nodes[p.0].children.push(id),
+                    None => self.roots.push(id),
+                }
+
+                Ok(id)
+            }
+
+            // Moves a node and everything below it under a new parent, keeping its local matrix
+            pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), &'static str> {
+                if id.0 >= self.nodes.len() || parent.is_some_and(|p| p.0 >= self.nodes.len()) {
+                    return Err("Node does not exist");
+                }
+
+                // Walking up from the new parent must not reach the node itself
+                let mut ancestor = parent;
+                while let Some(a) = ancestor {
+                    if a == id {
+                        return Err("Node cannot be parented to itself or one of its descendants");
+                    }
+                    ancestor = self.nodes[a.0].parent;
+                }
+
+                match self.nodes[id.0].parent {
+                    Some(old) => self.nodes[old.0].children.retain(|c| *c != id),
+                    None => self.roots.retain(|r| *r != id),
+                }
+                match parent {
+                    Some(p) => self.nodes[p.0].children.push(id),
+                    None => self.roots.push(id),
+                }
+                self.nodes[id.0].parent = parent;
+
+                Ok(())
+            }
+
+            pub fn node(&self, id: NodeId) -> Option<&Node> {
+                self.nodes.get(id.0)
+            }
+
+            pub fn mut_node(&mut self, id: NodeId) -> Option<&mut Node> {
+                self.nodes.get_mut(id.0)
+            }
+
+            pub fn find(&self, name: &str) -> Option<NodeId> {
+                self.nodes.iter().position(|n| n.name == name).map(NodeId)
+            }
+
+            pub fn roots(&self) -> &Vec<NodeId> {
+                &self.roots
+            }
+
+            // parent world * ... * local, walking up to the root
+            pub fn world_matrix(&self, id: NodeId) -> Option<Mat4> {
+                let mut node = self.node(id)?;
+                let mut world = node.local_matrix;
+                while let Some(parent) = node.parent {
+                    node = &self.nodes[parent.0];
+                    world = node.local_matrix * world;
+                }
+
+                Some(world)
+            }
+
+            // World matrices of every node indexed like the nodes, computed in a single pass from the roots
+            pub fn world_matrices(&self) -> Vec<Mat4> {
+                let mut world = vec![Mat4::identity(); self.nodes.len()];
+
+                let mut stack: Vec<(NodeId, Mat4)> = self.roots.iter().map(|r| (*r, Mat4::identity())).collect();
+                while let Some((id, parent)) = stack.pop() {
+                    let node = &self.nodes[id.0];
+                    world[id.0] = parent * node.local_matrix;
+                    stack.extend(node.children.iter().map(|c| (*c, world[id.0])));
+                }
+
+                world
+            }
+
+            // Every mesh in the scene together with the world matrix of its node
+            pub fn meshes(&self) -> Vec<(&Mesh, Mat4)> {
+                let world = self.world_matrices();
+
+                self.nodes.iter().zip(world)
+                    .filter_map(|(node, w)| node.mesh.as_ref().map(|m| (m, w)))
+                    .collect()
+            }
+        }
+    }
+}
+
+
+pub mod render {
+    pub struct Screen<T: Default> {
+        pixels: Vec<T>,
+        width: u32,
+        height: u32,
+    }
+
+    impl<T: Default> Screen<T> {
+        pub fn new(x: u32, y: u32) -> Self {
+            let mut screen = Screen {
+                pixels: Vec::new(),
+                width: x,
+                height: y,
+            };
+            screen.clear();
+            screen
+        }
+
+        pub fn resize(&mut self, x: u32, y: u32) {
+            self.width = x;
+            self.height = y;
+            self.clear();
+        }
+
+        pub fn clear(&mut self) {
+            let total = self.height * self.width;
+            self.pixels.clear();
+            for _ in 0..total {
+                self.pixels.push(T::default());
+            }
+        }
+        
+        pub fn set_pixel(&mut self, x: u32, y: u32, pixel: T) -> Result<(), &'static str> {
+            let i: usize = match (y * self.width + x).try_into() {
+                Ok(v) => v,
+                Err(_) => return Err("Desired pixel index does not fit into usize.")
+            };
+            if let Some(px) = self.pixels.get_mut(i) {
+                *px = pixel;
+            }
+            else {
+                return Err("Index out of bounds.");
+            }
+            Ok(())
+        }
+
+        pub fn push_pixels(&mut self, x: u32, y: u32, pixels: Vec<T>) -> Result<(), &'static str> {
+            let i: usize = match (y * self.width + x).try_into() {
+                Ok(v) => v,
+                Err(_) => return Err("Desired pixel index does not fit into usize.")
+            };
+            
+            for (offset, pixel) in pixels.into_iter().enumerate() {
+                if let Some(px) = self.pixels.get_mut(i + offset) {
+                    *px = pixel;
+                }
+            }
+
+            Ok(())
+        }
+
+        pub fn get_pixel(&self, x: u32, y: u32) -> Option<&T> { 
+            let i: usize = match (y * self.width + x).try_into() {
+                Ok(v) => v,
+                Err(_) => return None,
+            };
+            self.pixels.get(i) 
+        }
+
+        pub fn get_buffer(&self) -> &Vec<T> {
+            &self.pixels
+        }
+
+    }
+
+    impl<T: Default + Clone> Screen<T> {
+        // Like clear, but with a value other than the default
+        pub fn fill(&mut self, value: T) {
+            self.pixels.clear();
+            self.pixels.resize((self.height * self.width) as usize, value);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn push_pixels_writes_consecutive_cells() {
+            let mut screen: Screen<char> = Screen::new(4, 2);
+            screen.push_pixels(1, 0, vec!['a', 'b', 'c']).unwrap();
+
+            assert_eq!(screen.get_buffer()[..4], ['\0', 'a', 'b', 'c']);
+        }
+
+        #[test]
+        fn push_pixels_wraps_rows_and_drops_overflow() {
+            let mut screen: Screen<char> = Screen::new(2, 2);
+            screen.push_pixels(1, 0, vec!['a', 'b', 'c', 'd']).unwrap();
+
+            assert_eq!(screen.get_buffer()[..], ['\0', 'a', 'b', 'c']);
+        }
+    }
+}
+
+pub mod formats {
+    use std::{error::Error, fmt::Display};
+
+    #[derive(Debug)]
+    pub enum FormatError {
+        Io(std::io::Error),
+        // Line numbers start at 1
+        Parse { line: usize, message: String },
+        Invalid(String),
+    }
+
+    impl Display for FormatError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                FormatError::Io(e) => write!(f, "{e}"),
+                FormatError::Parse { line, message } => write!(f, "line {line}: {message}"),
+                FormatError::Invalid(message) => write!(f, "{message}"),
+            }
+        }
+    }
+
+    impl Error for FormatError {}
+
+    impl From<std::io::Error> for FormatError {
+        fn from(e: std::io::Error) -> Self {
+            FormatError::Io(e)
+        }
+    }
+
+    fn parse_error(line: usize, message: impl Into<String>) -> FormatError {
+        FormatError::Parse { line, message: message.into() }
+    }
+
+    pub mod obj {
+        use std::collections::HashMap;
+
+        use crate::render_math::{Mesh, vector::{Vector2, Vector3}};
+        use super::{FormatError, parse_error};
+
+        pub fn load(path: &str) -> Result<Mesh, FormatError> {
+            parse(&std::fs::read_to_string(path)?)
+        }
+
+        // Vertices are split per unique position/uv/normal combination, so attributes end up per-vertex.
+        // Polygons are triangulated as fans, groups and objects (g/o) become mesh groups.
+        pub fn parse(source: &str) -> Result<Mesh, FormatError> {
+            let mut positions: Vec<Vector3> = Vec::new();
+            let mut uvs: Vec<Vector2> = Vec::new();
+            let mut normals: Vec<Vector3> = Vec::new();
+
+            let mut mesh = Mesh::new();
+            let mut mesh_uvs = Vec::new();
+            let mut mesh_normals = Vec::new();
+            let mut has_uvs = false;
+            let mut has_normals = false;
+            let mut corners: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+            let mut has_faces = false;
+
+            for (i, raw) in source.lines().enumerate() {
+                let line = i + 1;
+                let content = raw.split('#').next().unwrap_or("").trim();
+                let mut parts = content.split_whitespace();
+                let keyword = match parts.next() {
+                    Some(k) => k,
+                    None => continue,
+                };
+                let args: Vec<&str> = parts.collect();
+
+                match keyword {
+                    "v" => {
+                        let v = parse_floats(&args, 3, line)?;
+                        positions.push(Vector3::new(v[0], v[1], v[2]));
+                    }
+                    "vt" => {
+                        let v = parse_floats(&args, 1, line)?;
+                        uvs.push(Vector2::new(v[0], v.get(1).copied().unwrap_or(0.0)));
+                    }
+                    "vn" => {
+                        let v = parse_floats(&args, 3, line)?;
+                        normals.push(Vector3::new(v[0], v[1], v[2]));
+                    }
+                    "f" => {
+                        if args.len() < 3 {
+                            return Err(parse_error(line, "face needs at least three vertices"));
+                        }
+                        has_faces = true;
+
+                        let mut indices = Vec::new();
+                        for arg in args {
+                            let corner = parse_corner(arg, positions.len(), uvs.len(), normals.len(), line)?;
+
+                            let index = match corners.get(&corner) {
+                                Some(index) => *index,
+                                None => {
+                                    let index = mesh.verts().len();
+                                    mesh.add_vertex(positions[corner.0]);
+                                    has_uvs |= corner.1.is_some();
+                                    has_normals |= corner.2.is_some();
+                                    mesh_uvs.push(corner.1.map_or(Vector2::new(0.0, 0.0), |i| uvs[i]));
+                                    mesh_normals.push(corner.2.map_or(Vector3::new(0.0, 0.0, 0.0), |i| normals[i]));
+                                    corners.insert(corner, index);
+                                    index
+                                }
+                            };
+                            indices.push(index);
+                        }
+
+                        mesh.add_face(&indices).map_err(|e| parse_error(line, e))?;
+                    }
+                    "g" | "o" => {
+                        let name = args.join(" ");
+                        mesh.begin_group(if name.is_empty() { "default" } else { &name });
+                    }
+                    // Materials, smoothing groups, lines and points are not supported by Mesh
+                    _ => {}
+                }
+            }
+
+            // A file without faces is a point cloud, keep every vertex
+            if !has_faces {
+                for p in positions {
+                    mesh.add_vertex(p);
+                }
+                return Ok(mesh);
+            }
+
+            if has_uvs {
+                mesh.set_uvs(mesh_uvs).map_err(|e| FormatError::Invalid(String::from(e)))?;
+            }
+            if has_normals {
+                mesh.set_normals(mesh_normals).map_err(|e| FormatError::Invalid(String::from(e)))?;
+            }
+
+            Ok(mesh)
+        }
+
+        fn parse_floats(args: &[&str], min: usize, line: usize) -> Result<Vec<f64>, FormatError> {
+            if args.len() < min {
+                return Err(parse_error(line, format!("expected at least {min} numbers, found {}", args.len())));
+            }
+
+            args.iter()
+                .map(|a| a.parse::<f64>().map_err(|_| parse_error(line, format!("invalid number '{a}'"))))
+                .collect()
+        }
+
+        // Face corners look like v, v/vt, v//vn or v/vt/vn
+        fn parse_corner(arg: &str, positions: usize, uvs: usize, normals: usize, line: usize) -> Result<(usize, Option<usize>, Option<usize>), FormatError> {
+            let mut parts = arg.split('/');
+
+            let v = match parts.next() {
+                Some(p) if !p.is_empty() => resolve_index(p, positions, line)?,
+                _ => return Err(parse_error(line, format!("missing vertex index in '{arg}'"))),
+            };
+            let vt = match parts.next() {
+                Some(p) if !p.is_empty() => Some(resolve_index(p, uvs, line)?),
+                _ => None,
+            };
+            let vn = match parts.next() {
+                Some(p) if !p.is_empty() => Some(resolve_index(p, normals, line)?),
+                _ => None,
+            };
+
+            Ok((v, vt, vn))
+        }
+
+        // OBJ indices start at 1, negative ones count back from the last element defined so far
+        fn resolve_index(s: &str, count: usize, line: usize) -> Result<usize, FormatError> {
+            let i: i64 = s.parse().map_err(|_| parse_error(line, format!("invalid index '{s}'")))?;
+
+            let resolved = if i > 0 {
+                i - 1
+            }
+            else {
+                count as i64 + i
+            };
+
+            if i == 0 || resolved < 0 || resolved >= count as i64 {
+                return Err(parse_error(line, format!("index {i} out of range, {count} elements defined")));
+            }
+
+            Ok(resolved as usize)
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            #[test]
+            fn resolves_negative_indices() {
+                let mesh = parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n").unwrap();
+                assert_eq!(mesh.verts(), &vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]);
+                assert_eq!(mesh.triangles(), &vec![[0, 1, 2]]);
+            }
+
+            #[test]
+            fn splits_vertices_with_different_attributes() {
+                let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 1\nvn 0 0 1\n\
+                    f 1/1/1 2/1/1 3/1/1\nf 1/2/1 3/1/1 2/1/1\n";
+                let mesh = parse(source).unwrap();
+
+                // Corner 1 is used with two different uvs, the other corners are shared
+                assert_eq!(mesh.verts().len(), 4);
+                assert_eq!(mesh.triangles(), &vec![[0, 1, 2], [3, 2, 1]]);
+                assert_eq!(mesh.verts()[3], Vector3::new(0.0, 0.0, 0.0));
+                assert_eq!(mesh.uvs()[0], Vector2::new(0.0, 0.0));
+                assert_eq!(mesh.uvs()[3], Vector2::new(1.0, 1.0));
+                assert_eq!(mesh.normals()[3], Vector3::new(0.0, 0.0, 1.0));
+            }
+
+            #[test]
+            fn triangulates_polygons_as_fans() {
+                let mesh = parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0.5 2 0\nv 0 1 0\nf 1 2 3 4 5\n").unwrap();
+                assert_eq!(mesh.triangles(), &vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+            }
+
+            #[test]
+            fn groups_and_objects_cover_their_triangles() {
+                let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+                    o first\nf 1 2 3\nf 2 4 3\ng second part\nf 1 2 4\ng\nf 1 3 4\n";
+                let mesh = parse(source).unwrap();
+                assert_eq!(mesh.groups(), vec![("first", 0..2), ("second part", 2..3), ("default", 3..4)]);
+            }
+
+            #[test]
+            fn parse_errors_report_their_line() {
+                let source = "v 0 0 0\n# comment\nv 1 0 0\nv 0 1 0\nf 1 2 4\n";
+                assert!(matches!(parse(source), Err(FormatError::Parse { line: 5, .. })));
+                assert!(matches!(parse("v 0 0 x\n"), Err(FormatError::Parse { line: 1, .. })));
+                assert!(matches!(parse("v 0 0 0\n\nf 1 1\n"), Err(FormatError::Parse { line: 3, .. })));
+            }
+        }
+    }
+
+    pub mod stl {
+        use std::{collections::HashMap, io::Write};
+
+        use crate::render_math::{Mesh, vector::Vector3};
+        use super::{FormatError, parse_error};
+
+        pub fn load(path: &str) -> Result<Mesh, FormatError> {
+            parse(&std::fs::read(path)?)
+        }
+
+        // Binary files may also start with "solid", so the size given by the triangle count decides
+        pub fn parse(bytes: &[u8]) -> Result<Mesh, FormatError> {
+            if bytes.len() >= 84 {
+                let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+                if binary_size(count) == Some(bytes.len()) {
+                    return parse_binary(bytes);
+                }
+            }
+
+            if bytes.starts_with(b"solid") {
+                let source = std::str::from_utf8(bytes).map_err(|_| FormatError::Invalid(String::from("ASCII STL is not valid UTF-8")))?;
+                return parse_ascii(source);
+            }
+
+            Err(FormatError::Invalid(String::from("neither an ASCII nor a binary STL file")))
+        }
+
+        pub fn parse_binary(bytes: &[u8]) -> Result<Mesh, FormatError> {
+            if bytes.len() < 84 {
+                return Err(FormatError::Invalid(String::from("binary STL is shorter than its header")));
+            }
+
+            let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+            if binary_size(count).is_none_or(|size| bytes.len() < size) {
+                return Err(FormatError::Invalid(format!("binary STL declares {count} triangles but is truncated")));
+            }
+
+            let mut builder = MeshBuilder::new();
+            for i in 0..count {
+                // Skip the 12 byte facet normal, the mesh gets its normals from the winding
+                let facet = &bytes[84 + i * 50 + 12..84 + i * 50 + 48];
+                let mut corners = [Vector3::new(0.0, 0.0, 0.0); 3];
+                for (c, corner) in corners.iter_mut().enumerate() {
+                    let read = |k: usize| {
+                        let at = c * 12 + k * 4;
+                        f64::from(f32::from_le_bytes([facet[at], facet[at + 1], facet[at + 2], facet[at + 3]]))
+                    };
+                    *corner = Vector3::new(read(0), read(1), read(2));
+                }
+                builder.add_facet(&corners).map_err(|e| FormatError::Invalid(format!("triangle {i}: {e}")))?;
+            }
+
+            Ok(builder.mesh)
+        }
+
+        // 80 byte header, triangle count and 50 bytes per triangle. None if it doesn't fit into usize.
+        fn binary_size(count: usize) -> Option<usize> {
+            count.checked_mul(50)?.checked_add(84)
+        }
+
+        pub fn parse_ascii(source: &str) -> Result<Mesh, FormatError> {
+            let mut builder = MeshBuilder::new();
+            let mut facet: Option<Vec<Vector3>> = None;
+
+            for (i, raw) in source.lines().enumerate() {
+                let line = i + 1;
+                let parts: Vec<&str> = raw.split_whitespace().collect();
+                let keyword = match parts.first() {
+                    Some(k) => *k,
+                    None => continue,
+                };
+
+                match keyword {
+                    "facet" => {
+                        if facet.is_some() {
+                            return Err(parse_error(line, "facet started before the previous one ended"));
+                        }
+                        facet = Some(Vec::new());
+                    }
+                    "vertex" => {
+                        let corners = facet.as_mut().ok_or_else(|| parse_error(line, "vertex outside of a facet"))?;
+                        if parts.len() != 4 {
+                            return Err(parse_error(line, "vertex needs three coordinates"));
+                        }
+                        let mut v = [0.0; 3];
+                        for (k, part) in parts[1..].iter().enumerate() {
+                            v[k] = part.parse().map_err(|_| parse_error(line, format!("invalid number '{part}'")))?;
+                        }
+                        corners.push(Vector3::new(v[0], v[1], v[2]));
+                    }
+                    "endfacet" => {
+                        let corners = facet.take().ok_or_else(|| parse_error(line, "endfacet without facet"))?;
+                        builder.add_facet(&corners).map_err(|e| parse_error(line, e))?;
+                    }
+                    "solid" | "outer" | "endloop" | "endsolid" => {}
+                    _ => return Err(parse_error(line, format!("unexpected keyword '{keyword}'"))),
+                }
+            }
+
+            if facet.is_some() {
+                return Err(FormatError::Invalid(String::from("file ended inside of a facet")));
+            }
+
+            Ok(builder.mesh)
+        }
+
+        pub fn save(mesh: &Mesh, path: &str, binary: bool) -> Result<(), FormatError> {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            if binary {
+                write_binary(mesh, &mut file)?;
+            }
+            else {
+                write_ascii(mesh, &mut file, "mesh")?;
+            }
+            file.flush()?;
+            Ok(())
+        }
+
+        // Untransformed vertices are written, facet normals are computed from the winding
+        pub fn write_ascii(mesh: &Mesh, w: &mut impl Write, name: &str) -> std::io::Result<()> {
+            writeln!(w, "solid {name}")?;
+            for t in mesh.triangles() {
+                let corners = [mesh.verts()[t[0]], mesh.verts()[t[1]], mesh.verts()[t[2]]];
+                let n = facet_normal(&corners);
+
+                writeln!(w, "  facet normal {} {} {}", n.x(), n.y(), n.z())?;
+                writeln!(w, "    outer loop")?;
+                for v in corners {
+                    writeln!(w, "      vertex {} {} {}", v.x(), v.y(), v.z())?;
+                }
+                writeln!(w, "    endloop")?;
+                writeln!(w, "  endfacet")?;
+            }
+            writeln!(w, "endsolid {name}")?;
+
+            Ok(())
+        }
+
+        pub fn write_binary(mesh: &Mesh, w: &mut impl Write) -> std::io::Result<()> {
+            let mut header = [0u8; 80];
+            let title = b"master_render_3d";
+            header[..title.len()].copy_from_slice(title);
+            w.write_all(&header)?;
+            w.write_all(&(mesh.triangles().len() as u32).to_le_bytes())?;
+
+            for t in mesh.triangles() {
+                let corners = [mesh.verts()[t[0]], mesh.verts()[t[1]], mesh.verts()[t[2]]];
+
+                for v in [facet_normal(&corners)].iter().chain(corners.iter()) {
+                    for c in [v.x(), v.y(), v.z()] {
+                        w.write_all(&(c as f32).to_le_bytes())?;
+                    }
+                }
+                // Attribute byte count, unused
+                w.write_all(&[0, 0])?;
+            }
+
+            Ok(())
+        }
+
+        fn facet_normal(corners: &[Vector3; 3]) -> Vector3 {
+            (corners[1] - corners[0]).cross(&(corners[2] - corners[0])).normalize()
+        }
+
+        // Merges vertices that have exactly the same position
+        struct MeshBuilder {
+            mesh: Mesh,
+            indices: HashMap<[u64; 3], usize>,
+        }
+
+        impl MeshBuilder {
+            fn new() -> Self {
+                MeshBuilder {
+                    mesh: Mesh::new(),
+                    indices: HashMap::new(),
+                }
+            }
+
+            fn add_facet(&mut self, corners: &[Vector3]) -> Result<(), &'static str> {
+                let indices: Vec<usize> = corners.iter().map(|v| self.index_of(*v)).collect();
+                self.mesh.add_face(&indices)
+            }
+
+            fn index_of(&mut self, v: Vector3) -> usize {
+                // + 0.0 turns -0.0 into 0.0 so both hash the same
+                let key = [(v.x() + 0.0).to_bits(), (v.y() + 0.0).to_bits(), (v.z() + 0.0).to_bits()];
+                if let Some(i) = self.indices.get(&key) {
+                    return *i;
+                }
+
+                let i = self.mesh.verts().len();
+                self.mesh.add_vertex(v);
+                self.indices.insert(key, i);
+                i
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            fn assert_same_triangles(parsed: &Mesh, original: &Mesh) {
+                assert_eq!(parsed.triangles().len(), original.triangles().len());
+                for (p, o) in parsed.triangles().iter().zip(original.triangles()) {
+                    for k in 0..3 {
+                        assert_eq!(parsed.verts()[p[k]], original.verts()[o[k]]);
+                    }
+                }
+            }
+
+            #[test]
+            fn ascii_round_trip_merges_duplicate_vertices() {
+                let cube = Mesh::cube(2.0);
+                assert_eq!(cube.verts().len(), 24);
+
+                let mut bytes = Vec::new();
+                write_ascii(&cube, &mut bytes, "cube").unwrap();
+                let parsed = parse(&bytes).unwrap();
+
+                assert_eq!(parsed.verts().len(), 8);
+                assert_same_triangles(&parsed, &cube);
+            }
+
+            #[test]
+            fn binary_round_trip_merges_duplicate_vertices() {
+                let cube = Mesh::cube(2.0);
+
+                let mut bytes = Vec::new();
+                write_binary(&cube, &mut bytes).unwrap();
+                assert_eq!(bytes.len(), 84 + 12 * 50);
+                let parsed = parse(&bytes).unwrap();
+
+                assert_eq!(parsed.verts().len(), 8);
+                assert_same_triangles(&parsed, &cube);
+            }
+
+            #[test]
+            fn rejects_truncated_binary_file() {
+                let mut bytes = Vec::new();
+                write_binary(&Mesh::cube(2.0), &mut bytes).unwrap();
+                bytes.truncate(bytes.len() - 1);
+
+                assert!(matches!(parse_binary(&bytes), Err(FormatError::Invalid(_))));
+                // Neither the declared size nor "solid" at the start
+                assert!(matches!(parse(&bytes), Err(FormatError::Invalid(_))));
+
+                bytes[80..84].copy_from_slice(&u32::MAX.to_le_bytes());
+                assert!(matches!(parse_binary(&bytes), Err(FormatError::Invalid(_))));
+            }
+        }
+    }
+
+    pub mod ply {
+        use std::io::Write;
+
+        use crate::render_math::{Mesh, vector::{Vector2, Vector3}};
+        use super::{FormatError, parse_error};
+
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        pub enum PlyFormat {
+            Ascii,
+            BinaryLittleEndian,
+            BinaryBigEndian,
+        }
+
+        pub fn load(path: &str) -> Result<Mesh, FormatError> {
+            parse(&std::fs::read(path)?)
+        }
+
+        // Reads positions, normals (nx/ny/nz), colors (red/green/blue), texture coordinates (u/v or s/t)
+        // and faces (vertex_indices). Other elements and properties are skipped.
+        pub fn parse(bytes: &[u8]) -> Result<Mesh, FormatError> {
+            let (header, body) = parse_header(bytes)?;
+            let mut reader = ValueReader::new(header.format, body, header.lines)?;
+
+            let mut mesh = Mesh::new();
+            let mut normals = Vec::new();
+            let mut colors = Vec::new();
+            let mut uvs = Vec::new();
+
+            for element in header.elements.iter() {
+                let find = |names: &[&str]| element.properties.iter().position(|p| names.contains(&p.name.as_str()));
+
+                match element.name.as_str() {
+                    "vertex" => {
+                        let position = [find(&["x"]), find(&["y"]), find(&["z"])];
+                        let normal = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+                        let color = [find(&["red", "r"]), find(&["green", "g"]), find(&["blue", "b"])];
+                        let uv = [find(&["u", "s", "texture_u"]), find(&["v", "t", "texture_v"])];
+                        if position.iter().any(|p| p.is_none()) {
+                            return Err(FormatError::Invalid(String::from("vertex element needs x, y and z properties")));
+                        }
+
+                        for _ in 0..element.count {
+                            let values = reader.read_element(element)?;
+                            let scalar = |i: Option<usize>| i.map_or(0.0, |i| values[i].scalar());
+
+                            mesh.add_vertex(Vector3::new(scalar(position[0]), scalar(position[1]), scalar(position[2])));
+                            if normal.iter().all(|n| n.is_some()) {
+                                normals.push(Vector3::new(scalar(normal[0]), scalar(normal[1]), scalar(normal[2])));
+                            }
+                            if color.iter().all(|c| c.is_some()) {
+                                let channel = |i: Option<usize>| {
+                                    let i = i.expect("Checked above");
+                                    // Floating point colors are stored in [0; 1]
+                                    if element.properties[i].kind.is_float() {
+                                        (values[i].scalar() * 255.0).round().clamp(0.0, 255.0) as u8
+                                    }
+                                    else {
+                                        values[i].scalar().clamp(0.0, 255.0) as u8
+                                    }
+                                };
+                                colors.push([channel(color[0]), channel(color[1]), channel(color[2])]);
+                            }
+                            if uv.iter().all(|c| c.is_some()) {
+                                uvs.push(Vector2::new(scalar(uv[0]), scalar(uv[1])));
+                            }
+                        }
+                    }
+                    "face" => {
+                        let indices = find(&["vertex_indices", "vertex_index"])
+                            .ok_or_else(|| FormatError::Invalid(String::from("face element needs a vertex_indices list")))?;
+
+                        for i in 0..element.count {
+                            let values = reader.read_element(element)?;
+                            let face = values[indices].list().iter()
+                                .map(|v| match *v >= 0.0 && v.fract() == 0.0 {
+                                    true => Ok(*v as usize),
+                                    false => Err(reader.error(format!("face {i}: invalid vertex index {v}"))),
+                                })
+                                .collect::<Result<Vec<usize>, FormatError>>()?;
+                            mesh.add_face(&face).map_err(|e| reader.error(format!("face {i}: {e}")))?;
+                        }
+                    }
+                    _ => {
+                        for _ in 0..element.count {
+                            reader.read_element(element)?;
+                        }
+                    }
+                }
+            }
+
+            let invalid = |e: &str| FormatError::Invalid(String::from(e));
+            mesh.set_normals(normals).map_err(invalid)?;
+            mesh.set_colors(colors).map_err(invalid)?;
+            mesh.set_uvs(uvs).map_err(invalid)?;
+
+            Ok(mesh)
+        }
+
+        pub fn save(mesh: &Mesh, path: &str, format: PlyFormat) -> Result<(), FormatError> {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            write(mesh, &mut file, format)?;
+            file.flush()?;
+            Ok(())
+        }
+
+        // Untransformed vertices are written, together with whichever attributes the mesh has
+        pub fn write(mesh: &Mesh, w: &mut impl Write, format: PlyFormat) -> std::io::Result<()> {
+            let has_normals = !mesh.normals().is_empty();
+            let has_colors = !mesh.colors().is_empty();
+            let has_uvs = !mesh.uvs().is_empty();
+
+            writeln!(w, "ply")?;
+            writeln!(w, "format {} 1.0", match format {
+                PlyFormat::Ascii => "ascii",
+                PlyFormat::BinaryLittleEndian => "binary_little_endian",
+                PlyFormat::BinaryBigEndian => "binary_big_endian",
+            })?;
+            writeln!(w, "element vertex {}", mesh.verts().len())?;
+            for name in ["x", "y", "z"] {
+                writeln!(w, "property double {name}")?;
+            }
+            if has_normals {
+                for name in ["nx", "ny", "nz"] {
+                    writeln!(w, "property double {name}")?;
+                }
+            }
+            if has_colors {
+                for name in ["red", "green", "blue"] {
+                    writeln!(w, "property uchar {name}")?;
+                }
+            }
+            if has_uvs {
+                for name in ["u", "v"] {
+                    writeln!(w, "property double {name}")?;
+                }
+            }
+            writeln!(w, "element face {}", mesh.triangles().len())?;
+            writeln!(w, "property list uchar uint vertex_indices")?;
+            writeln!(w, "end_header")?;
+
+            let double = |w: &mut dyn Write, v: f64| -> std::io::Result<()> {
+                match format {
+                    PlyFormat::Ascii => write!(w, "{v} "),
+                    PlyFormat::BinaryLittleEndian => w.write_all(&v.to_le_bytes()),
+                    PlyFormat::BinaryBigEndian => w.write_all(&v.to_be_bytes()),
+                }
+            };
+            let end_line = |w: &mut dyn Write| -> std::io::Result<()> {
+                if format == PlyFormat::Ascii {
+                    writeln!(w)?;
+                }
+                Ok(())
+            };
+
+            for (i, v) in mesh.verts().iter().enumerate() {
+                for c in [v.x(), v.y(), v.z()] {
+                    double(w, c)?;
+                }
+                if has_normals {
+                    let n = mesh.normals()[i];
+                    for c in [n.x(), n.y(), n.z()] {
+                        double(w, c)?;
+                    }
+                }
+                if has_colors {
+                    for c in mesh.colors()[i] {
+                        match format {
+                            PlyFormat::Ascii => write!(w, "{c} ")?,
+                            _ => w.write_all(&[c])?,
+                        }
+                    }
+                }
+                if has_uvs {
+                    let uv = mesh.uvs()[i];
+                    double(w, uv.x())?;
+                    double(w, uv.y())?;
+                }
+                end_line(w)?;
+            }
+
+            for t in mesh.triangles() {
+                match format {
+                    PlyFormat::Ascii => write!(w, "3 {} {} {}", t[0], t[1], t[2])?,
+                    _ => {
+                        w.write_all(&[3])?;
+                        for i in t {
+                            let i = *i as u32;
+                            w.write_all(&if format == PlyFormat::BinaryLittleEndian { i.to_le_bytes() } else { i.to_be_bytes() })?;
+                        }
+                    }
+                }
+                end_line(w)?;
+            }
+
+            Ok(())
+        }
+
+        #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+        enum ScalarType {
+            Int8,
+            UInt8,
+            Int16,
+            UInt16,
+            Int32,
+            UInt32,
+            Float32,
+            Float64,
+        }
+
+        impl ScalarType {
+            fn parse(name: &str) -> Option<Self> {
+                match name {
+                    "char" | "int8" => Some(ScalarType::Int8),
+                    "uchar" | "uint8" => Some(ScalarType::UInt8),
+                    "short" | "int16" => Some(ScalarType::Int16),
+                    "ushort" | "uint16" => Some(ScalarType::UInt16),
+                    "int" | "int32" => Some(ScalarType::Int32),
+                    "uint" | "uint32" => Some(ScalarType::UInt32),
+                    "float" | "float32" => Some(ScalarType::Float32),
+                    "double" | "float64" => Some(ScalarType::Float64),
+                    _ => None,
+                }
+            }
+
+            fn size(&self) -> usize {
+                match self {
+                    ScalarType::Int8 | ScalarType::UInt8 => 1,
+                    ScalarType::Int16 | ScalarType::UInt16 => 2,
+                    ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+                    ScalarType::Float64 => 8,
+                }
+            }
+
+            fn is_float(&self) -> bool {
+                matches!(self, ScalarType::Float32 | ScalarType::Float64)
+            }
+
+            fn decode(&self, b: &[u8], little_endian: bool) -> f64 {
+                macro_rules! read {
+                    ($t:ty, $n:expr) => {{
+                        let mut raw = [0u8; $n];
+                        raw.copy_from_slice(&b[..$n]);
+                        if little_endian { <$t>::from_le_bytes(raw) } else { <$t>::from_be_bytes(raw) }
+                    }};
+                }
+
+                match self {
+                    ScalarType::Int8 => f64::from(b[0] as i8),
+                    ScalarType::UInt8 => f64::from(b[0]),
+                    ScalarType::Int16 => f64::from(read!(i16, 2)),
+                    ScalarType::UInt16 => f64::from(read!(u16, 2)),
+                    ScalarType::Int32 => f64::from(read!(i32, 4)),
+                    ScalarType::UInt32 => f64::from(read!(u32, 4)),
+                    ScalarType::Float32 => f64::from(read!(f32, 4)),
+                    ScalarType::Float64 => read!(f64, 8),
+                }
+            }
+        }
+
+        struct Property {
+            name: String,
+            kind: ScalarType,
+            // Type of the length prefix for list properties
+            list: Option<ScalarType>,
+        }
+
+        struct Element {
+            name: String,
+            count: usize,
+            properties: Vec<Property>,
+        }
+
+        struct Header {
+            format: PlyFormat,
+            elements: Vec<Element>,
+            lines: usize,
+        }
+
+        enum Value {
+            Scalar(f64),
+            List(Vec<f64>),
+        }
+
+        impl Value {
+            fn scalar(&self) -> f64 {
+                match self {
+                    Value::Scalar(v) => *v,
+                    Value::List(l) => l.first().copied().unwrap_or(0.0),
+                }
+            }
+
+            fn list(&self) -> Vec<f64> {
+                match self {
+                    Value::Scalar(v) => vec![*v],
+                    Value::List(l) => l.clone(),
+                }
+            }
+        }
+
+        // Returns the header and the bytes following end_header
+        fn parse_header(bytes: &[u8]) -> Result<(Header, &[u8]), FormatError> {
+            let mut pos = 0;
+            let mut line = 0;
+            let mut format = None;
+            let mut elements: Vec<Element> = Vec::new();
+
+            loop {
+                let end = bytes[pos..].iter().position(|b| *b == b'\n')
+                    .ok_or_else(|| FormatError::Invalid(String::from("header has no end_header line")))?;
+                let text = std::str::from_utf8(&bytes[pos..pos + end])
+                    .map_err(|_| parse_error(line + 1, "header is not valid text"))?
+                    .trim();
+                pos += end + 1;
+                line += 1;
+
+                let parts: Vec<&str> = text.split_whitespace().collect();
+                if line == 1 {
+                    if text != "ply" {
+                        return Err(parse_error(line, "file does not start with 'ply'"));
+                    }
+                    continue;
+                }
+
+                match parts.first().copied() {
+                    Some("format") => {
+                        format = Some(match parts.get(1).copied() {
+                            Some("ascii") => PlyFormat::Ascii,
+                            Some("binary_little_endian") => PlyFormat::BinaryLittleEndian,
+                            Some("binary_big_endian") => PlyFormat::BinaryBigEndian,
+                            _ => return Err(parse_error(line, format!("unknown format '{text}'"))),
+                        });
+                    }
+                    Some("element") => {
+                        if parts.len() != 3 {
+                            return Err(parse_error(line, "element needs a name and a count"));
+                        }
+                        let count = parts[2].parse().map_err(|_| parse_error(line, format!("invalid element count '{}'", parts[2])))?;
+                        elements.push(Element { name: String::from(parts[1]), count, properties: Vec::new() });
+                    }
+                    Some("property") => {
+                        let element = elements.last_mut().ok_or_else(|| parse_error(line, "property before any element"))?;
+                        let type_of = |name: &str| ScalarType::parse(name).ok_or_else(|| parse_error(line, format!("unknown property type '{name}'")));
+
+                        let property = if parts.get(1) == Some(&"list") {
+                            if parts.len() != 5 {
+                                return Err(parse_error(line, "list property needs a count type, an item type and a name"));
+                            }
+                            Property { name: String::from(parts[4]), kind: type_of(parts[3])?, list: Some(type_of(parts[2])?) }
+                        }
+                        else {
+                            if parts.len() != 3 {
+                                return Err(parse_error(line, "property needs a type and a name"));
+                            }
+                            Property { name: String::from(parts[2]), kind: type_of(parts[1])?, list: None }
+                        };
+                        element.properties.push(property);
+                    }
+                    Some("end_header") => break,
+                    Some("comment") | Some("obj_info") | None => {}
+                    Some(other) => return Err(parse_error(line, format!("unexpected header keyword '{other}'"))),
+                }
+            }
+
+            let format = format.ok_or_else(|| FormatError::Invalid(String::from("header has no format line")))?;
+            Ok((Header { format, elements, lines: line }, &bytes[pos..]))
+        }
+
+        struct ValueReader<'a> {
+            format: PlyFormat,
+            bytes: &'a [u8],
+            pos: usize,
+
+            // ASCII bodies are read line by line, one element per line
+            lines: std::str::Lines<'a>,
+            line: usize,
+        }
+
+        impl<'a> ValueReader<'a> {
+            fn new(format: PlyFormat, bytes: &'a [u8], header_lines: usize) -> Result<Self, FormatError> {
+                let text = if format == PlyFormat::Ascii {
+                    std::str::from_utf8(bytes).map_err(|_| FormatError::Invalid(String::from("ASCII body is not valid text")))?
+                }
+                else {
+                    ""
+                };
+
+                Ok(ValueReader {
+                    format,
+                    bytes,
+                    pos: 0,
+
+                    lines: text.lines(),
+                    line: header_lines,
+                })
+            }
+
+            fn error(&self, message: String) -> FormatError {
+                match self.format {
+                    PlyFormat::Ascii => parse_error(self.line, message),
+                    _ => FormatError::Invalid(format!("byte {}: {message}", self.pos)),
+                }
+            }
+
+            fn read_element(&mut self, element: &Element) -> Result<Vec<Value>, FormatError> {
+                match self.format {
+                    PlyFormat::Ascii => self.read_ascii(element),
+                    _ => self.read_binary(element),
+                }
+            }
+
+            fn read_ascii(&mut self, element: &Element) -> Result<Vec<Value>, FormatError> {
+                let text = loop {
+                    self.line += 1;
+                    match self.lines.next() {
+                        Some(l) if l.trim().is_empty() => continue,
+                        Some(l) => break l,
+                        None => return Err(self.error(format!("file ended while reading element '{}'", element.name))),
+                    }
+                };
+
+                let mut tokens = text.split_whitespace();
+                let mut next = || -> Result<f64, FormatError> {
+                    let token = tokens.next().ok_or_else(|| parse_error(self.line, format!("too few values for element '{}'", element.name)))?;
+                    token.parse().map_err(|_| parse_error(self.line, format!("invalid number '{token}'")))
+                };
+
+                let mut values = Vec::new();
+                for property in element.properties.iter() {
+                    match property.list {
+                        Some(_) => {
+                            let count = self.list_length(next()?)?;
+                            // Not preallocated, the count is only trusted as far as there are values on the line
+                            let mut list = Vec::new();
+                            for _ in 0..count {
+                                list.push(next()?);
+                            }
+                            values.push(Value::List(list));
+                        }
+                        None => values.push(Value::Scalar(next()?)),
+                    }
+                }
+
+                Ok(values)
+            }
+
+            fn read_binary(&mut self, element: &Element) -> Result<Vec<Value>, FormatError> {
+                let mut values = Vec::new();
+                for property in element.properties.iter() {
+                    match property.list {
+                        Some(count_type) => {
+                            let count = self.read_scalar(count_type, &element.name)?;
+                            let count = self.list_length(count)?;
+                            if count.checked_mul(property.kind.size()).is_none_or(|size| size > self.bytes.len() - self.pos) {
+                                return Err(self.error(format!("list of {count} values is longer than the rest of the file")));
+                            }
+                            let mut list = Vec::with_capacity(count);
+                            for _ in 0..count {
+                                list.push(self.read_scalar(property.kind, &element.name)?);
+                            }
+                            values.push(Value::List(list));
+                        }
+                        None => values.push(Value::Scalar(self.read_scalar(property.kind, &element.name)?)),
+                    }
+                }
+
+                Ok(values)
+            }
+
+            fn list_length(&self, count: f64) -> Result<usize, FormatError> {
+                if count < 0.0 || count.fract() != 0.0 {
+                    return Err(self.error(format!("invalid list length {count}")));
+                }
+                Ok(count as usize)
+            }
+
+            fn read_scalar(&mut self, kind: ScalarType, element: &str) -> Result<f64, FormatError> {
+                if self.pos + kind.size() > self.bytes.len() {
+                    return Err(self.error(format!("file ended while reading element '{element}'")));
+                }
+
+                let value = kind.decode(&self.bytes[self.pos..], self.format == PlyFormat::BinaryLittleEndian);
+                self.pos += kind.size();
+                Ok(value)
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            const ASCII_HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n";
+
+            #[test]
+            fn parses_ascii_face() {
+                let mesh = parse(format!("{ASCII_HEADER}3 0 1 2\n").as_bytes()).unwrap();
+                assert_eq!(mesh.triangles(), &vec![[0, 1, 2]]);
+            }
+
+            #[test]
+            fn rejects_huge_ascii_list_length() {
+                assert!(matches!(parse(format!("{ASCII_HEADER}1e18 1 2 3\n").as_bytes()), Err(FormatError::Parse { .. })));
+                assert!(matches!(parse(format!("{ASCII_HEADER}-3 0 1 2\n").as_bytes()), Err(FormatError::Parse { .. })));
+            }
+
+            #[test]
+            fn rejects_negative_vertex_index() {
+                assert!(matches!(parse(format!("{ASCII_HEADER}3 0 -1 2\n").as_bytes()), Err(FormatError::Parse { .. })));
+            }
+
+            #[test]
+            fn rejects_binary_list_longer_than_file() {
+                let mut bytes = b"ply\nformat binary_little_endian 1.0\nelement vertex 0\nproperty float x\nproperty float y\nproperty float z\n\
+                    element face 1\nproperty list uint int vertex_indices\nend_header\n".to_vec();
+                bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+                bytes.extend_from_slice(&[0; 12]);
+                assert!(matches!(parse(&bytes), Err(FormatError::Invalid(_))));
+            }
+        }
+    }
+
+    pub mod gltf {
+        use std::path::Path;
+
+        use serde_json::Value;
+
+        use crate::render_math::{Mesh, matrix::Mat4, quaternion::Quaternion, transform::Transform, vector::{Vector2, Vector3}};
+        use super::FormatError;
+
+        // Loads .gltf (embedded or with buffers next to it) and .glb files.
+        // Every primitive of every mesh in the default scene becomes one Mesh. The node's world transform, parents
+        // included, is split into the mesh's translation, rotation and scale matrices. A world transform with shear,
+        // e.g. a rotated child of a non-uniformly scaled parent, can't be split and is stored whole as the
+        // translation matrix, so replacing that matrix later drops the rotation and scale too.
+        pub fn load(path: &str) -> Result<Vec<Mesh>, FormatError> {
+            let bytes = std::fs::read(path)?;
+            let base = Path::new(path).parent();
+
+            if bytes.starts_with(b"glTF") {
+                parse_glb(&bytes, base)
             }
             else {
-                return Err("Index out of bounds.");
+                parse_gltf(&bytes, base)
+            }
+        }
+
+        // Relative buffer URIs are resolved against base, without one only data URIs can be loaded
+        pub fn parse_gltf(json: &[u8], base: Option<&Path>) -> Result<Vec<Mesh>, FormatError> {
+            let document = parse_json(json)?;
+            let buffers = load_buffers(&document, base, None)?;
+            build_meshes(&document, &buffers)
+        }
+
+        pub fn parse_glb(bytes: &[u8], base: Option<&Path>) -> Result<Vec<Mesh>, FormatError> {
+            let u32_at = |at: usize| -> Result<u32, FormatError> {
+                bytes.get(at..at + 4)
+                    .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .ok_or_else(|| invalid("GLB file is truncated"))
+            };
+
+            if !bytes.starts_with(b"glTF") {
+                return Err(invalid("GLB file does not start with 'glTF'"));
+            }
+            if u32_at(4)? != 2 {
+                return Err(invalid("only GLB version 2 is supported"));
+            }
+
+            let mut json = None;
+            let mut binary = None;
+            let mut pos = 12;
+            while pos + 8 <= bytes.len() {
+                let length = u32_at(pos)? as usize;
+                let kind = u32_at(pos + 4)?;
+                let chunk = bytes.get(pos + 8..pos + 8 + length).ok_or_else(|| invalid("GLB chunk is truncated"))?;
+                match kind {
+                    0x4E4F534A => json = Some(chunk),
+                    0x004E4942 => binary = Some(chunk.to_vec()),
+                    _ => {}
+                }
+                pos += 8 + length;
+            }
+
+            let document = parse_json(json.ok_or_else(|| invalid("GLB file has no JSON chunk"))?)?;
+            let buffers = load_buffers(&document, base, binary)?;
+            build_meshes(&document, &buffers)
+        }
+
+        fn invalid(message: &str) -> FormatError {
+            FormatError::Invalid(String::from(message))
+        }
+
+        fn parse_json(json: &[u8]) -> Result<Value, FormatError> {
+            serde_json::from_slice(json).map_err(|e| FormatError::Parse { line: e.line(), message: e.to_string() })
+        }
+
+        fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+            value.get(key).and_then(|v| v.as_array()).map_or(&[], |v| v.as_slice())
+        }
+
+        fn index(value: &Value, key: &str) -> Option<usize> {
+            value.get(key).and_then(|v| v.as_u64()).map(|v| v as usize)
+        }
+
+        fn numbers(value: &Value, key: &str) -> Option<Vec<f64>> {
+            value.get(key)?.as_array()?.iter().map(|v| v.as_f64()).collect()
+        }
+
+        fn load_buffers(document: &Value, base: Option<&Path>, mut binary: Option<Vec<u8>>) -> Result<Vec<Vec<u8>>, FormatError> {
+            let mut buffers = Vec::new();
+
+            for (i, buffer) in array(document, "buffers").iter().enumerate() {
+                let data = match buffer.get("uri").and_then(|u| u.as_str()) {
+                    Some(uri) if uri.starts_with("data:") => {
+                        let (_, encoded) = uri.split_once(";base64,").ok_or_else(|| invalid("only base64 data URIs are supported"))?;
+                        decode_base64(encoded).ok_or_else(|| invalid("buffer data URI is not valid base64"))?
+                    }
+                    Some(uri) => {
+                        let base = base.ok_or_else(|| invalid("external buffers need the path of the glTF file"))?;
+                        std::fs::read(base.join(uri))?
+                    }
+                    // The first buffer of a GLB file without a URI is the binary chunk
+                    None if i == 0 => binary.take().ok_or_else(|| invalid("buffer 0 has no URI and there is no binary chunk"))?,
+                    None => return Err(FormatError::Invalid(format!("buffer {i} has no URI"))),
+                };
+                buffers.push(data);
+            }
+
+            Ok(buffers)
+        }
+
+        fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+            let value_of = |c: u8| -> Option<u32> {
+                match c {
+                    b'A'..=b'Z' => Some(u32::from(c - b'A')),
+                    b'a'..=b'z' => Some(u32::from(c - b'a') + 26),
+                    b'0'..=b'9' => Some(u32::from(c - b'0') + 52),
+                    b'+' | b'-' => Some(62),
+                    b'/' | b'_' => Some(63),
+                    _ => None,
+                }
+            };
+
+            let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+            let mut bits = 0u32;
+            let mut count = 0;
+            for c in encoded.bytes().filter(|c| *c != b'=' && !c.is_ascii_whitespace()) {
+                bits = (bits << 6) | value_of(c)?;
+                count += 6;
+                if count >= 8 {
+                    count -= 8;
+                    out.push((bits >> count) as u8);
+                    bits &= (1 << count) - 1;
+                }
+            }
+
+            Some(out)
+        }
+
+        fn build_meshes(document: &Value, buffers: &[Vec<u8>]) -> Result<Vec<Mesh>, FormatError> {
+            let nodes = array(document, "nodes");
+
+            // The default scene, or every node that isn't a child of another one
+            let roots: Vec<usize> = match array(document, "scenes").get(index(document, "scene").unwrap_or(0)) {
+                Some(scene) => array(scene, "nodes").iter().filter_map(|n| n.as_u64()).map(|n| n as usize).collect(),
+                None => {
+                    let children: Vec<usize> = nodes.iter()
+                        .flat_map(|n| array(n, "children").iter().filter_map(|c| c.as_u64()).map(|c| c as usize))
+                        .collect();
+                    (0..nodes.len()).filter(|n| !children.contains(n)).collect()
+                }
+            };
+
+            let mut meshes = Vec::new();
+            for root in roots {
+                visit_node(document, buffers, root, Mat4::identity(), 0, &mut meshes)?;
+            }
+
+            Ok(meshes)
+        }
+
+        fn visit_node(document: &Value, buffers: &[Vec<u8>], node_index: usize, parent: Mat4, depth: usize, meshes: &mut Vec<Mesh>) -> Result<(), FormatError> {
+            if depth > array(document, "nodes").len() {
+                return Err(invalid("node hierarchy contains a cycle"));
+            }
+            let node = array(document, "nodes").get(node_index).ok_or_else(|| FormatError::Invalid(format!("node {node_index} does not exist")))?;
+
+            let (translation, rotation, scale) = match numbers(node, "matrix") {
+                Some(m) if m.len() == 16 => {
+                    // glTF matrices are column major
+                    let mut values = [[0.0; 4]; 4];
+                    for (i, v) in m.iter().enumerate() {
+                        values[i % 4][i / 4] = *v;
+                    }
+                    (Mat4::new(values), Mat4::identity(), Mat4::identity())
+                }
+                _ => {
+                    let t = numbers(node, "translation").unwrap_or(vec![0.0, 0.0, 0.0]);
+                    let r = numbers(node, "rotation").unwrap_or(vec![0.0, 0.0, 0.0, 1.0]);
+                    let s = numbers(node, "scale").unwrap_or(vec![1.0, 1.0, 1.0]);
+                    if t.len() != 3 || r.len() != 4 || s.len() != 3 {
+                        return Err(FormatError::Invalid(format!("node {node_index} has a malformed transform")));
+                    }
+                    (
+                        Mat4::translation(Vector3::new(t[0], t[1], t[2])),
+                        // glTF stores quaternions as x, y, z, w
+                        Quaternion::new(r[3], r[0], r[1], r[2]).to_mat4(),
+                        Mat4::scale(Vector3::new(s[0], s[1], s[2])),
+                    )
+                }
+            };
+
+            let world = parent * translation * rotation * scale;
+
+            if let Some(mesh_index) = index(node, "mesh") {
+                let mesh = array(document, "meshes").get(mesh_index).ok_or_else(|| FormatError::Invalid(format!("mesh {mesh_index} does not exist")))?;
+                let name = mesh.get("name").and_then(|n| n.as_str());
+                for (i, primitive) in array(mesh, "primitives").iter().enumerate() {
+                    let mut result = read_primitive(document, buffers, primitive, name)
+                        .map_err(|e| FormatError::Invalid(format!("mesh {mesh_index}, primitive {i}: {e}")))?;
+                    set_world_transform(&mut result, world);
+                    meshes.push(result);
+                }
+            }
+
+            for child in array(node, "children").iter().filter_map(|c| c.as_u64()) {
+                visit_node(document, buffers, child as usize, world, depth + 1, meshes)?;
+            }
+
+            Ok(())
+        }
+
+        fn set_world_transform(mesh: &mut Mesh, world: Mat4) {
+            // Splitting a sheared matrix silently loses the shear, which to_mat4 won't give back
+            let split = Transform::from_mat4(&world).filter(|t| {
+                t.to_mat4().values().iter().flatten().zip(world.values().iter().flatten())
+                    .all(|(a, b)| (a - b).abs() <= 1e-9 * (1.0 + b.abs()))
+            });
+
+            match split {
+                Some(transform) => mesh.set_transform(&transform),
+                None => {
+                    mesh.set_translation_matrix(world);
+                    mesh.set_rotation_matrix(Mat4::identity());
+                    mesh.set_scale_matrix(Mat4::identity());
+                }
+            }
+        }
+
+        // The primitive's triangles are grouped under the glTF mesh name, if it has one
+        fn read_primitive(document: &Value, buffers: &[Vec<u8>], primitive: &Value, name: Option<&str>) -> Result<Mesh, FormatError> {
+            let attributes = primitive.get("attributes").ok_or_else(|| invalid("primitive has no attributes"))?;
+            // Elements of an accessor all have the same number of components, checked once here so they can be indexed below
+            let attribute = |name: &str, components: usize| -> Result<Option<Vec<Vec<f64>>>, FormatError> {
+                let values = match index(attributes, name) {
+                    Some(i) => read_accessor(document, buffers, i)?,
+                    None => return Ok(None),
+                };
+                if values.first().is_some_and(|v| v.len() < components) {
+                    return Err(FormatError::Invalid(format!("{name} must have at least {components} components")));
+                }
+                Ok(Some(values))
+            };
+
+            let positions = attribute("POSITION", 3)?.ok_or_else(|| invalid("primitive has no POSITION attribute"))?;
+            let mut mesh = Mesh::new();
+            for p in positions.iter() {
+                mesh.add_vertex(Vector3::new(p[0], p[1], p[2]));
+            }
+
+            let count_error = |e: &str| FormatError::Invalid(String::from(e));
+            if let Some(normals) = attribute("NORMAL", 3)? {
+                mesh.set_normals(normals.iter().map(|n| Vector3::new(n[0], n[1], n[2])).collect()).map_err(count_error)?;
+            }
+            if let Some(uvs) = attribute("TEXCOORD_0", 2)? {
+                mesh.set_uvs(uvs.iter().map(|t| Vector2::new(t[0], t[1])).collect()).map_err(count_error)?;
+            }
+            if let Some(colors) = attribute("COLOR_0", 3)? {
+                let channel = |c: f64| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+                mesh.set_colors(colors.iter().map(|c| [channel(c[0]), channel(c[1]), channel(c[2])]).collect()).map_err(count_error)?;
+            }
+
+            let indices: Vec<usize> = match index(primitive, "indices") {
+                Some(i) => read_accessor(document, buffers, i)?.iter().map(|v| v[0] as usize).collect(),
+                None => (0..positions.len()).collect(),
+            };
+
+            if let Some(name) = name {
+                mesh.begin_group(name);
+            }
+
+            let add = |mesh: &mut Mesh, a: usize, b: usize, c: usize| mesh.add_triangle(a, b, c).map_err(count_error);
+            match index(primitive, "mode").unwrap_or(4) {
+                // Points
+                0 => {}
+                4 => {
+                    for t in indices.chunks_exact(3) {
+                        add(&mut mesh, t[0], t[1], t[2])?;
+                    }
+                }
+                // Triangle strip, every other triangle flipped to keep the winding
+                5 => {
+                    for i in 0..indices.len().saturating_sub(2) {
+                        if i % 2 == 0 {
+                            add(&mut mesh, indices[i], indices[i + 1], indices[i + 2])?;
+                        }
+                        else {
+                            add(&mut mesh, indices[i + 1], indices[i], indices[i + 2])?;
+                        }
+                    }
+                }
+                // Triangle fan
+                6 => {
+                    for i in 1..indices.len().saturating_sub(1) {
+                        add(&mut mesh, indices[0], indices[i], indices[i + 1])?;
+                    }
+                }
+                mode => return Err(FormatError::Invalid(format!("primitive mode {mode} (lines) is not supported"))),
+            }
+
+            Ok(mesh)
+        }
+
+        // Every element is returned as its list of components, normalized integers mapped to [0; 1] or [-1; 1]
+        fn read_accessor(document: &Value, buffers: &[Vec<u8>], accessor_index: usize) -> Result<Vec<Vec<f64>>, FormatError> {
+            let accessor = array(document, "accessors").get(accessor_index)
+                .ok_or_else(|| FormatError::Invalid(format!("accessor {accessor_index} does not exist")))?;
+            let fail = |message: &str| FormatError::Invalid(format!("accessor {accessor_index}: {message}"));
+
+            if accessor.get("sparse").is_some() {
+                return Err(fail("sparse accessors are not supported"));
+            }
+
+            let count = index(accessor, "count").ok_or_else(|| fail("missing count"))?;
+            let components = match accessor.get("type").and_then(|t| t.as_str()) {
+                Some("SCALAR") => 1,
+                Some("VEC2") => 2,
+                Some("VEC3") => 3,
+                Some("VEC4") => 4,
+                Some("MAT2") => 4,
+                Some("MAT3") => 9,
+                Some("MAT4") => 16,
+                _ => return Err(fail("unknown type")),
+            };
+            let component_type = index(accessor, "componentType").ok_or_else(|| fail("missing componentType"))?;
+            let size = match component_type {
+                5120 | 5121 => 1,
+                5122 | 5123 => 2,
+                5125 | 5126 => 4,
+                _ => return Err(fail("unknown componentType")),
+            };
+            let normalized = accessor.get("normalized").and_then(|n| n.as_bool()).unwrap_or(false);
+
+            // Without a buffer view nothing bounds count, such accessors only make sense together with sparse ones
+            let view_index = index(accessor, "bufferView").ok_or_else(|| fail("accessors without a buffer view are not supported"))?;
+            let view = array(document, "bufferViews").get(view_index).ok_or_else(|| fail("buffer view does not exist"))?;
+            let buffer = buffers.get(index(view, "buffer").unwrap_or(0)).ok_or_else(|| fail("buffer does not exist"))?;
+            let view_start = index(view, "byteOffset").unwrap_or(0);
+            let view_end = index(view, "byteLength")
+                .and_then(|length| view_start.checked_add(length))
+                .filter(|end| *end <= buffer.len())
+                .ok_or_else(|| fail("buffer view does not fit into its buffer"))?;
+            let start = view_start.checked_add(index(accessor, "byteOffset").unwrap_or(0)).ok_or_else(|| fail("byteOffset is too large"))?;
+            let element_size = components * size;
+            let stride = index(view, "byteStride").unwrap_or(element_size);
+            if stride < element_size {
+                return Err(fail("byteStride is smaller than an element"));
+            }
+
+            // Checked before allocating anything, count comes straight from the file
+            let end = match count {
+                0 => Some(start),
+                _ => (count - 1).checked_mul(stride).and_then(|n| n.checked_add(element_size)).and_then(|n| n.checked_add(start)),
+            };
+            if end.is_none_or(|end| end > view_end) {
+                return Err(fail("reads past the end of its buffer view"));
+            }
+
+            let mut result = Vec::with_capacity(count);
+            for i in 0..count {
+                let mut element = Vec::with_capacity(components);
+                for c in 0..components {
+                    let at = start + i * stride + c * size;
+                    let b = buffer.get(at..at + size).ok_or_else(|| fail("reads past the end of its buffer"))?;
+
+                    let value = match component_type {
+                        5120 => if normalized { (f64::from(b[0] as i8) / 127.0).max(-1.0) } else { f64::from(b[0] as i8) },
+                        5121 => if normalized { f64::from(b[0]) / 255.0 } else { f64::from(b[0]) },
+                        5122 => {
+                            let v = f64::from(i16::from_le_bytes([b[0], b[1]]));
+                            if normalized { (v / 32767.0).max(-1.0) } else { v }
+                        }
+                        5123 => {
+                            let v = f64::from(u16::from_le_bytes([b[0], b[1]]));
+                            if normalized { v / 65535.0 } else { v }
+                        }
+                        5125 => f64::from(u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                        _ => f64::from(f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                    };
+                    element.push(value);
+                }
+                result.push(element);
+            }
+
+            Ok(result)
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+
+            // One triangle at the origin: 36 zero bytes holding three VEC3 float positions
+            fn document(accessors: &str) -> String {
+                format!(r#"{{
+                    "buffers": [{{ "byteLength": 36, "uri": "data:application/octet-stream;base64,{}" }}],
+                    "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+                    "accessors": [{accessors}],
+                    "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "NORMAL": 1 }} }}] }}],
+                    "nodes": [{{ "mesh": 0 }}]
+                }}"#, "A".repeat(48))
+            }
+
+            const POSITIONS: &str = r#"{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" }"#;
+
+            #[test]
+            fn loads_valid_primitive() {
+                let json = document(&format!("{POSITIONS}, {POSITIONS}"));
+                let meshes = parse_gltf(json.as_bytes(), None).unwrap();
+                assert_eq!(meshes[0].verts().len(), 3);
+                assert_eq!(meshes[0].normals().len(), 3);
+            }
+
+            #[test]
+            fn rejects_attribute_with_too_few_components() {
+                let normals = r#"{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "SCALAR" }"#;
+                let json = document(&format!("{POSITIONS}, {normals}"));
+                assert!(matches!(parse_gltf(json.as_bytes(), None), Err(FormatError::Invalid(_))));
+            }
+
+            #[test]
+            fn rejects_count_larger_than_buffer_view() {
+                let normals = r#"{ "bufferView": 0, "componentType": 5126, "count": 1000000000000000, "type": "VEC3" }"#;
+                let json = document(&format!("{POSITIONS}, {normals}"));
+                assert!(matches!(parse_gltf(json.as_bytes(), None), Err(FormatError::Invalid(_))));
+
+                let normals = r#"{ "componentType": 5126, "count": 1000000000000000, "type": "VEC3" }"#;
+                let json = document(&format!("{POSITIONS}, {normals}"));
+                assert!(matches!(parse_gltf(json.as_bytes(), None), Err(FormatError::Invalid(_))));
+            }
+
+            // Positions (0, 0, 0), (1, 0, 0) and (0, 1, 0)
+            fn triangle_bytes() -> Vec<u8> {
+                [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0].iter().flat_map(|f| f.to_le_bytes()).collect()
+            }
+
+            fn triangle_document(buffer: &str, nodes: &str) -> String {
+                format!(r#"{{
+                    "buffers": [{buffer}],
+                    "bufferViews": [{{ "buffer": 0, "byteLength": 36 }}],
+                    "accessors": [{POSITIONS}],
+                    "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0 }} }}] }}],
+                    "nodes": [{nodes}]
+                }}"#)
+            }
+
+            fn glb(json: &str, binary: &[u8]) -> Vec<u8> {
+                let mut json = json.as_bytes().to_vec();
+                // Chunks are padded to 4 bytes, JSON with spaces
+                json.resize(json.len().next_multiple_of(4), b' ');
+
+                let mut bytes = b"glTF".to_vec();
+                bytes.extend_from_slice(&2u32.to_le_bytes());
+                bytes.extend_from_slice(&((12 + 8 + json.len() + 8 + binary.len()) as u32).to_le_bytes());
+                for (kind, chunk) in [(0x4E4F534Au32, json.as_slice()), (0x004E4942, binary)] {
+                    bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(&kind.to_le_bytes());
+                    bytes.extend_from_slice(chunk);
+                }
+                bytes
+            }
+
+            #[test]
+            fn loads_glb_binary_chunk() {
+                let json = triangle_document(r#"{ "byteLength": 36 }"#, r#"{ "mesh": 0 }"#);
+                let meshes = parse_glb(&glb(&json, &triangle_bytes()), None).unwrap();
+                assert_eq!(meshes[0].verts(), &vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]);
+
+                let mut truncated = glb(&json, &triangle_bytes());
+                truncated.pop();
+                assert!(matches!(parse_glb(&truncated, None), Err(FormatError::Invalid(_))));
+                assert!(matches!(parse_glb(b"glTF\x01\0\0\0\0\0\0\0", None), Err(FormatError::Invalid(_))));
+            }
+
+            #[test]
+            fn loads_external_buffer_relative_to_base() {
+                let dir = std::env::temp_dir().join(format!("master_render_3d_gltf_{}", std::process::id()));
+                std::fs::create_dir_all(&dir).unwrap();
+                std::fs::write(dir.join("triangle.bin"), triangle_bytes()).unwrap();
+
+                let json = triangle_document(r#"{ "byteLength": 36, "uri": "triangle.bin" }"#, r#"{ "mesh": 0 }"#);
+                let loaded = parse_gltf(json.as_bytes(), Some(&dir));
+                let without_base = parse_gltf(json.as_bytes(), None);
+                std::fs::remove_dir_all(&dir).unwrap();
+
+                assert_eq!(loaded.unwrap()[0].verts()[1], Vector3::new(1.0, 0.0, 0.0));
+                assert!(matches!(without_base, Err(FormatError::Invalid(_))));
+            }
+
+            fn embedded_buffer() -> String {
+                format!(r#"{{ "byteLength": 36, "uri": "data:application/octet-stream;base64,{}" }}"#, "A".repeat(48))
+            }
+
+            #[test]
+            fn parent_transform_is_split_into_translation_rotation_and_scale() {
+                let nodes = r#"{ "scale": [2, 2, 2], "children": [1] }, { "mesh": 0, "translation": [1, 0, 0] }"#;
+                let json = triangle_document(&embedded_buffer(), nodes);
+                let mut mesh = parse_gltf(json.as_bytes(), None).unwrap().remove(0);
+                assert_eq!(mesh.model_matrix().transform_point(&Vector3::new(1.0, 0.0, 0.0)), Vector3::new(4.0, 0.0, 0.0));
+
+                // Moving the mesh keeps the parent's scale
+                mesh.set_translation_matrix(Mat4::translation(Vector3::new(5.0, 0.0, 0.0)));
+                assert_eq!(mesh.model_matrix().transform_point(&Vector3::new(1.0, 0.0, 0.0)), Vector3::new(7.0, 0.0, 0.0));
+            }
+
+            #[test]
+            fn sheared_world_transform_is_kept_whole() {
+                let (sin, cos) = std::f64::consts::FRAC_PI_8.sin_cos();
+                let nodes = format!(r#"{{ "scale": [2, 1, 1], "children": [1] }}, {{ "mesh": 0, "rotation": [0, 0, {sin}, {cos}] }}"#);
+                let json = triangle_document(&embedded_buffer(), &nodes);
+                let mesh = parse_gltf(json.as_bytes(), None).unwrap().remove(0);
+
+                let half = std::f64::consts::FRAC_1_SQRT_2;
+                let moved = mesh.model_matrix().transform_point(&Vector3::new(1.0, 0.0, 0.0));
+                assert!(moved.approx_eq(&Vector3::new(2.0 * half, half, 0.0), 1e-9));
+            }
+        }
+    }
+}
+
+pub mod ansi {
+    use std::{collections::HashMap, error::Error, fmt::Write as _, io::{self, Sink, Stdout, Write}};
+
+    use crate::{render::Screen, render_math::vector::*};
+    use crate::render_math::{Mesh, camera::Camera, matrix::Mat4, scene::Scene};
+    use image_helper::image::*;
+
+    pub struct ANSIRenderer<W: Write = Stdout> {
+        helper: ANSIHelper<W>,
+
+        // Cells drawn in this frame and the one before, so flush can clear what was not drawn again
+        currently_set_pixels: Screen<bool>,
+        previously_set_pixels: Screen<bool>,
+
+        depth_buffer: Screen<f64>,
+        depth_convention: DepthConvention,
+        depth_test: DepthTest,
+        depth_write: bool,
+        clear_depth: f64,
+
+        render_mode: RenderMode,
+        camera: Option<Camera>,
+
+        // Scratch buffer for projected mesh vertices, kept between frames to avoid reallocating
+        projected_verts: Vec<Vector4>,
+    }
+
+    // How render_mesh draws the faces of a mesh
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum RenderMode {
+        Solid,
+        Wireframe,
+        // Wireframe without the edges hidden behind the mesh's own faces
+        HiddenLine,
+    }
+
+    // Whether a cell at depth z may replace the one already stored in the depth buffer
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum DepthTest {
+        Less,
+        LessEqual,
+        Greater,
+        GreaterEqual,
+        Always,
+        Never,
+    }
+
+    impl DepthTest {
+        fn passes(self, z: f64, stored: f64) -> bool {
+            match self {
+                DepthTest::Less => z < stored,
+                DepthTest::LessEqual => z <= stored,
+                DepthTest::Greater => z > stored,
+                DepthTest::GreaterEqual => z >= stored,
+                DepthTest::Always => true,
+                DepthTest::Never => false,
+            }
+        }
+    }
+
+    // How depth values are ordered. Depths the renderer computes from distances to the viewer
+    // (meshes, rasterize_triangle, rasterize_line_3d) are stored in this convention, while the z given
+    // to draw_at, draw_bitmap, draw_image_2d and rasterize_line is stored as is.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum DepthConvention {
+        // Smaller z is nearer, the buffer is cleared to +infinity
+        NearIsSmaller,
+        // Larger z is nearer, distances are stored negated and the buffer is cleared to -infinity
+        ReversedZ,
+    }
+
+    impl DepthConvention {
+        pub fn default_test(self) -> DepthTest {
+            match self {
+                DepthConvention::NearIsSmaller => DepthTest::LessEqual,
+                DepthConvention::ReversedZ => DepthTest::GreaterEqual,
+            }
+        }
+
+        pub fn default_clear_depth(self) -> f64 {
+            match self {
+                DepthConvention::NearIsSmaller => f64::INFINITY,
+                DepthConvention::ReversedZ => f64::NEG_INFINITY,
+            }
+        }
+
+        pub fn encode(self, distance: f64) -> f64 {
+            match self {
+                DepthConvention::NearIsSmaller => distance,
+                DepthConvention::ReversedZ => -distance,
+            }
+        }
+    }
+
+    // Pulls wireframe lines slightly towards the viewer so they win against the faces they lie on
+    const WIREFRAME_DEPTH_BIAS: f64 = 0.05;
+
+    impl ANSIRenderer<Stdout> {
+        pub fn new(w: u32, h: u32) -> Self {
+            ANSIRenderer::with_output(w, h, io::stdout())
+        }
+    }
+
+    impl ANSIRenderer<Sink> {
+        // Writes nothing, every flushed frame is only recorded, see frame()
+        pub fn headless(w: u32, h: u32) -> Self {
+            let mut renderer = ANSIRenderer::with_output(w, h, io::sink());
+            renderer.set_recording(true);
+            renderer
+        }
+    }
+
+    impl<W: Write> ANSIRenderer<W> {
+        // Frames are written to output instead of stdout, e.g. a file, a socket or a Vec<u8>
+        pub fn with_output(w: u32, h: u32, output: W) -> Self {
+            let convention = DepthConvention::NearIsSmaller;
+
+            ANSIRenderer {
+                helper: ANSIHelper::with_output(w, h, output),
+
+                currently_set_pixels: Screen::new(w, h),
+                previously_set_pixels: Screen::new(w, h),
+
+                depth_buffer: Self::cleared_depth_buffer(w, h, convention.default_clear_depth()),
+                depth_convention: convention,
+                depth_test: convention.default_test(),
+                depth_write: true,
+                clear_depth: convention.default_clear_depth(),
+
+                render_mode: RenderMode::Solid,
+                camera: None,
+
+                projected_verts: Vec::new(),
+            }
+        }
+
+        pub fn helper(&mut self) -> &mut ANSIHelper<W> {
+            &mut self.helper
+        }
+
+        // Keeps a copy of every flushed frame, e.g. for snapshot tests
+        pub fn set_recording(&mut self, enabled: bool) {
+            self.helper.set_recording(enabled);
+        }
+
+        // The last frame flushed while recording
+        pub fn frame(&self) -> Option<&Frame> {
+            self.helper.frame()
+        }
+
+        fn cleared_depth_buffer(w: u32, h: u32, clear_depth: f64) -> Screen<f64> {
+            let mut buffer = Screen::new(w, h);
+            buffer.fill(clear_depth);
+            buffer
+        }
+
+        // Also resets the depth test and clear depth to the defaults of the convention and clears the depth buffer
+        pub fn set_depth_convention(&mut self, convention: DepthConvention) {
+            self.depth_convention = convention;
+            self.depth_test = convention.default_test();
+            self.clear_depth = convention.default_clear_depth();
+            self.depth_buffer.fill(self.clear_depth);
+        }
+
+        pub fn depth_convention(&self) -> DepthConvention {
+            self.depth_convention
+        }
+
+        // Value every cell of the depth buffer starts a frame with
+        pub fn set_clear_depth(&mut self, depth: f64) {
+            self.clear_depth = depth;
+            self.depth_buffer.fill(depth);
+        }
+
+        pub fn clear_depth(&self) -> f64 {
+            self.clear_depth
+        }
+
+        pub fn set_depth_test(&mut self, test: DepthTest) {
+            self.depth_test = test;
+        }
+
+        pub fn depth_test(&self) -> DepthTest {
+            self.depth_test
+        }
+
+        // When disabled cells are still depth tested, but don't occlude anything drawn after them
+        pub fn set_depth_write(&mut self, enabled: bool) {
+            self.depth_write = enabled;
+        }
+
+        pub fn depth_write(&self) -> bool {
+            self.depth_write
+        }
+
+        pub fn set_render_mode(&mut self, mode: RenderMode) {
+            self.render_mode = mode;
+        }
+
+        pub fn render_mode(&self) -> RenderMode {
+            self.render_mode
+        }
+
+        // Without a camera meshes are projected orthographically, vertex x/y being the screen position
+        pub fn set_camera(&mut self, camera: Option<Camera>) {
+            self.camera = camera;
+        }
+
+        pub fn camera(&mut self) -> Option<&mut Camera> {
+            self.camera.as_mut()
+        }
+
+        // Every vertex placed by parent * model, in homogeneous screen space: x / w and y / w are the screen position
+        // and z is the depth. With a camera w is the distance along the view direction and z is replaced by it,
+        // without one w is 1. Interpolating linearly here is correct, which is what clipping relies on.
+        // The buffer is taken out of projected_verts and has to be handed back, see with_projected.
+        fn project_verts(&mut self, mesh: &Mesh, parent: &Mat4) -> Vec<Vector4> {
+            let world = *parent * mesh.model_matrix();
+            let mut projected = std::mem::take(&mut self.projected_verts);
+            projected.clear();
+
+            match &self.camera {
+                Some(camera) => {
+                    let matrix = camera.screen_matrix(self.helper.width, self.helper.height) * world;
+                    projected.extend(mesh.verts().iter().map(|v| {
+                        let mut clip = Vector4::from(v) * &matrix;
+                        *clip.mut_z() = clip.w();
+                        clip
+                    }));
+                }
+                None => projected.extend(mesh.verts().iter().map(|v| Vector4::from(&world.transform_point(v)))),
+            }
+
+            projected
+        }
+
+        // Runs f with the projected vertices of the mesh, reusing the same allocation every time
+        fn with_projected(&mut self, mesh: &Mesh, parent: &Mat4, f: impl FnOnce(&mut Self, &[Vector4]) -> Result<(), &'static str>) -> Result<(), &'static str> {
+            let verts = self.project_verts(mesh, parent);
+            let result = f(self, &verts);
+            self.projected_verts = verts;
+            result
+        }
+
+        // Range of w that is drawn, everything outside of it is clipped
+        fn depth_range(&self) -> (f64, f64) {
+            match &self.camera {
+                Some(camera) => (camera.near(), camera.far()),
+                None => (f64::NEG_INFINITY, f64::INFINITY),
+            }
+        }
+
+        // Every char of s takes a cell of its own and is depth tested on its own. Like ANSIHelper::write,
+        // the string wraps onto the next row. Returns false if no cell passed the depth test.
+        pub fn draw_at(&mut self, x: u32, y: u32, s: &str, z: f64, style: Option<ANSIStyle>) -> Result<bool, &'static str> {
+            let width = u64::from(self.helper.width);
+            if width == 0 {
+                return Ok(false);
+            }
+            let start = u64::from(y) * width + u64::from(x);
+            let mut drawn = false;
+            let mut buffer = [0; 4];
+
+            for (i, c) in s.chars().enumerate() {
+                let index = start + i as u64;
+                if index / width >= u64::from(self.helper.height) {
+                    break;
+                }
+                let (x, y) = ((index % width) as u32, (index / width) as u32);
+
+                // Ignore pixel if something above it already exists in the depth buffer
+                if !self.write_depth(x, y, z) {
+                    continue;
+                }
+
+                // WARN
+                // This if statement does not appear to have significant performance improvement
+                //if self.helper.current_pos.x() != pos.x() || self.helper.current_pos.y() != pos.y() {
+                self.helper.go_to(x, y);
+                //}
+                if let (false, Some(st)) = (drawn, style) {
+                    self.helper.set_style(st);
+                }
+                self.helper.write(c.encode_utf8(&mut buffer))?;
+                _ = self.currently_set_pixels.set_pixel(x, y, true);
+                drawn = true;
+            }
+
+            Ok(drawn)
+        }
+
+        // Depth tests the cell and stores z if it passes. Also used on its own to occupy the
+        // depth buffer without drawing anything, for hidden line removal.
+        fn write_depth(&mut self, x: u32, y: u32, z: f64) -> bool {
+            // Cells outside of the screen have no depth and are never occluded
+            if x >= self.helper.width || y >= self.helper.height {
+                return true;
+            }
+
+            let stored = *self.depth_buffer.get_pixel(x, y).expect("Cell is inside the screen");
+            if !self.depth_test.passes(z, stored) {
+                return false;
+            }
+            if self.depth_write {
+                _ = self.depth_buffer.set_pixel(x, y, z);
+            }
+
+            true
+        }
+
+        // Colors set this way are overridden by the cell colors, see ANSIHelper::set_style
+        pub fn set_style(&mut self, style: ANSIStyle) {
+            self.helper.set_style(style);
+        }
+
+        // Colors of every cell drawn from now on, None is the terminal's default
+        pub fn set_colors(&mut self, foreground: Option<Color>, background: Option<Color>) {
+            self.helper.set_colors(foreground, background);
+        }
+
+        pub fn colors(&self) -> (Option<Color>, Option<Color>) {
+            self.helper.colors()
+        }
+
+        pub fn clear_at(&mut self, x: u32, y: u32) {
+            let (foreground, background) = self.helper.colors();
+            self.helper.set_colors(None, None);
+            self.helper.go_to(x, y);
+            _ = self.helper.write(" "); // Error doesn't matter. We are clearing the pixel.
+            self.helper.set_colors(foreground, background);
+        }
+
+        pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+            // Clear pixels that are now unset. Does not produce flicker like full clear.
+            let width = self.helper.width;
+            for i in 0..self.previously_set_pixels.get_buffer().len() {
+                if self.previously_set_pixels.get_buffer()[i] && !self.currently_set_pixels.get_buffer()[i] {
+                    self.clear_at(i as u32 % width, i as u32 / width);
+                }
             }
+            // Flush
+            self.helper.flush()?;
+            std::mem::swap(&mut self.previously_set_pixels, &mut self.currently_set_pixels);
+            self.currently_set_pixels.clear();
+            self.depth_buffer.fill(self.clear_depth);
+
             Ok(())
         }
 
-        pub fn push_pixels(&mut self, x: u32, y: u32, pixels: Vec<T>) -> Result<(), &'static str> {
-            let i: usize = match (y * self.width + x).try_into() {
-                Ok(v) => v,
-                Err(_) => return Err("Desired pixel index does not fit into usize.")
+        pub fn go_to_immediate(&mut self, x: u32, y: u32) -> io::Result<()> {
+            self.helper.go_to_immediate(x, y)
+        }
+
+        pub fn go_to(&mut self, x: u32, y: u32) {
+            self.helper.go_to(x, y);
+        }
+
+        pub fn clear(&mut self) -> io::Result<()> {
+            self.helper.full_clear()
+        }
+
+        pub fn rasterize_line(&mut self, from: Vector2i, to: Vector2i, z: f64, glyph: Option<&str>, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            let c = glyph.unwrap_or(slope_glyph(f64::from(to.x()) - f64::from(from.x()), f64::from(to.y()) - f64::from(from.y())));
+
+            let from = Vector2::new(f64::from(from.x()), f64::from(from.y()));
+            let to = Vector2::new(f64::from(to.x()), f64::from(to.y()));
+            let Some((start, end)) = self.clip_to_screen(from, to) else {
+                return Ok(());
             };
-            
-            for pixel in pixels {
-                if let Some(px) = self.pixels.get_mut(i) {
-                    *px = pixel;
+
+            for (x, y) in line_cells(segment_point(from, to, start), segment_point(from, to, end)) {
+                self.draw_clipped(x, y, c, z, style)?;
+            }
+
+            Ok(())
+        }
+
+        // Parameters of the part of the segment that is on screen, with a cell of margin for rounding.
+        // Keeps far away endpoints from making the rasterizer walk across cells nobody sees.
+        fn clip_to_screen(&self, from: Vector2, to: Vector2) -> Option<(f64, f64)> {
+            if !(from.x().is_finite() && from.y().is_finite() && to.x().is_finite() && to.y().is_finite()) {
+                return None;
+            }
+
+            // Liang-Barsky
+            let (mut start, mut end) = (0.0, 1.0);
+            let delta = to - from;
+            let bounds = [
+                (-delta.x(), from.x() + 1.0),
+                (delta.x(), f64::from(self.helper.width) - from.x()),
+                (-delta.y(), from.y() + 1.0),
+                (delta.y(), f64::from(self.helper.height) - from.y()),
+            ];
+            for (p, q) in bounds {
+                if p == 0.0 {
+                    if q < 0.0 {
+                        return None;
+                    }
+                    continue;
+                }
+
+                let t = q / p;
+                if p < 0.0 {
+                    start = f64::max(start, t);
+                }
+                else {
+                    end = f64::min(end, t);
+                }
+            }
+
+            if start > end {
+                return None;
+            }
+            Some((start, end))
+        }
+
+        // Same as rasterize_line, but depth is interpolated between the two endpoints
+        pub fn rasterize_line_3d(&mut self, from: Vector3, to: Vector3, glyph: Option<&str>, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            self.line_with_depth(Vector4::from(&from), Vector4::from(&to), glyph, style)
+        }
+
+        // Takes points as returned by project_verts, the part outside of the depth range is clipped
+        fn line_with_depth(&mut self, from: Vector4, to: Vector4, glyph: Option<&str>, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            let (near, far) = self.depth_range();
+            let Some((from, to)) = clip_segment(from, to, near, far) else {
+                return Ok(());
+            };
+
+            let (screen_from, screen_to) = (screen_position(&from), screen_position(&to));
+            let Some((start, end)) = self.clip_to_screen(screen_from, screen_to) else {
+                return Ok(());
+            };
+            let delta = screen_to - screen_from;
+            let c = glyph.unwrap_or(slope_glyph(delta.x(), delta.y()));
+
+            let cells = line_cells(segment_point(screen_from, screen_to, start), segment_point(screen_from, screen_to, end));
+            let last = (cells.len() - 1).max(1) as f64;
+            for (i, (x, y)) in cells.into_iter().enumerate() {
+                // Perspective correct: z / w and 1 / w are linear on screen, z itself is not
+                let t = start + (end - start) * i as f64 / last;
+                let inverse_w = (1.0 - t) / from.w() + t / to.w();
+                let z = ((1.0 - t) * from.z() / from.w() + t * to.z() / to.w()) / inverse_w;
+                self.draw_clipped(x, y, c, self.depth_convention.encode(z), style)?;
+            }
+
+            Ok(())
+        }
+
+        fn draw_clipped(&mut self, x: i32, y: i32, c: &str, z: f64, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            // Clip everything outside of the screen, draw_at would wrap it onto the next row
+            if x < 0 || y < 0 || x as u32 >= self.helper.width || y as u32 >= self.helper.height {
+                return Ok(());
+            }
+
+            if self.draw_at(x as u32, y as u32, c, z, style)? && style.is_some() {
+                // Don't let the style bleed into the following cells
+                self.helper.set_style(ANSIStyle::None);
+            }
+
+            Ok(())
+        }
+
+        pub fn draw_bitmap(&mut self, bitmap: &Bitmap, position: Vector2i, z: f64) -> Result<(), &'static str> {
+            for y in 0..bitmap.height {
+                for x in 0..bitmap.width {
+                    let i = (y * bitmap.width + x) as usize;
+                    let c = bitmap.cells[i];
+                    if c == '\0' || Some(c) == bitmap.transparent {
+                        continue;
+                    }
+
+                    let pos = position + Vector2i::new(x as i32, y as i32);
+                    self.draw_clipped(pos.x(), pos.y(), &c.to_string(), z, bitmap.styles[i])?;
+                }
+            }
+
+            Ok(())
+        }
+
+        pub fn draw_image_2d(&mut self, image: &ImageData, position: Vector2i, z: f64) -> Result<(), &'static str> {
+            for y in 0..image.height() {
+                for x in 0..image.width() {
+                    let pixel = image.get_pixel_at(x, y);
+
+                    if pixel.r() != 0 {
+                        let new_pos = position + Vector2i::new(x as i32, y as i32);
+                        self.draw_at(new_pos.x() as u32, new_pos.y() as u32, "&", z, None)?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        pub fn rasterize_vertices(&mut self, mesh: &Mesh, max_distance: f64) -> Result<(), &'static str> {
+            self.with_projected(mesh, &Mat4::identity(), |r, verts| r.draw_vertices(verts, max_distance))
+        }
+
+        fn draw_vertices(&mut self, verts: &[Vector4], max_distance: f64) -> Result<(), &'static str> {
+            let (near, far) = self.depth_range();
+
+            for v in verts.iter().filter(|v| v.w() >= near && v.w() <= far) {
+                let pos = screen_position(v);
+                let depth = v.z();
+
+                // Only visit the cells inside the square around the vertex instead of the whole screen
+                let min_x = (pos.x() - max_distance).ceil().max(0.0);
+                let max_x = (pos.x() + max_distance).floor().min(f64::from(self.helper.width) - 1.0);
+                let min_y = (pos.y() - max_distance).ceil().max(0.0);
+                let max_y = (pos.y() + max_distance).floor().min(f64::from(self.helper.height) - 1.0);
+                if min_x > max_x || min_y > max_y {
+                    continue;
+                }
+
+                for x in (min_x as u32)..=(max_x as u32) {
+                    for y in (min_y as u32)..=(max_y as u32) {
+                        let pixel_pos = Vector2::new(f64::from(x), f64::from(y));
+                        if pos.distance(&pixel_pos) <= max_distance {
+                            self.draw_at(x, y, depth_glyph(depth), self.depth_convention.encode(depth), None)?;
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        // Edge function rasterizer. Both windings are filled, depth is interpolated with barycentric weights.
+        pub fn rasterize_triangle(&mut self, a: Vector3, b: Vector3, c: Vector3, glyph: Option<&str>) -> Result<(), &'static str> {
+            self.fill_triangle(Vector4::from(&a), Vector4::from(&b), Vector4::from(&c), glyph, false)
+        }
+
+        // Takes points as returned by project_verts. The triangle is clipped to the depth range,
+        // so one that passes through the near plane keeps the part in front of it.
+        fn fill_clipped(&mut self, a: Vector4, b: Vector4, c: Vector4, glyph: Option<&str>, depth_only: bool) -> Result<(), &'static str> {
+            let (near, far) = self.depth_range();
+            if [a, b, c].iter().all(|v| v.w() >= near && v.w() <= far) {
+                return self.fill_triangle(a, b, c, glyph, depth_only);
+            }
+
+            let polygon = clip_polygon(&[a, b, c], near, far);
+            for i in 1..polygon.len().saturating_sub(1) {
+                self.fill_triangle(polygon[0], polygon[i], polygon[i + 1], glyph, depth_only)?;
+            }
+
+            Ok(())
+        }
+
+        fn fill_triangle(&mut self, a: Vector4, b: Vector4, c: Vector4, glyph: Option<&str>, depth_only: bool) -> Result<(), &'static str> {
+            let (pos_a, pos_b, pos_c) = (screen_position(&a), screen_position(&b), screen_position(&c));
+            let area = edge_function(&pos_a, &pos_b, &pos_c);
+            if area.abs() < f64::EPSILON {
+                return Ok(());
+            }
+
+            // Perspective correct depth: z / w and 1 / w are linear on screen, z itself is not
+            let inverse_w = [1.0 / a.w(), 1.0 / b.w(), 1.0 / c.w()];
+            let z_over_w = [a.z() * inverse_w[0], b.z() * inverse_w[1], c.z() * inverse_w[2]];
+
+            let min = pos_a.min(&pos_b).min(&pos_c);
+            let max = pos_a.max(&pos_b).max(&pos_c);
+            let min_x = min.x().ceil().max(0.0);
+            let max_x = max.x().floor().min(f64::from(self.helper.width) - 1.0);
+            let min_y = min.y().ceil().max(0.0);
+            let max_y = max.y().floor().min(f64::from(self.helper.height) - 1.0);
+            if min_x > max_x || min_y > max_y {
+                return Ok(());
+            }
+
+            for y in (min_y as u32)..=(max_y as u32) {
+                for x in (min_x as u32)..=(max_x as u32) {
+                    let p = Vector2::new(f64::from(x), f64::from(y));
+
+                    let w_a = edge_function(&pos_b, &pos_c, &p) / area;
+                    let w_b = edge_function(&pos_c, &pos_a, &p) / area;
+                    let w_c = edge_function(&pos_a, &pos_b, &p) / area;
+                    if w_a < 0.0 || w_b < 0.0 || w_c < 0.0 {
+                        continue;
+                    }
+
+                    let z = (w_a * z_over_w[0] + w_b * z_over_w[1] + w_c * z_over_w[2])
+                        / (w_a * inverse_w[0] + w_b * inverse_w[1] + w_c * inverse_w[2]);
+                    let depth = self.depth_convention.encode(z);
+                    if depth_only {
+                        self.write_depth(x, y, depth);
+                    }
+                    else {
+                        self.draw_at(x, y, glyph.unwrap_or(depth_glyph(z)), depth, None)?;
+                    }
                 }
             }
 
             Ok(())
         }
 
-        pub fn get_pixel(&self, x: u32, y: u32) -> Option<&T> { 
-            let i: usize = match (y * self.width + x).try_into() {
-                Ok(v) => v,
-                Err(_) => return None,
-            };
-            self.pixels.get(i) 
+        pub fn rasterize_faces(&mut self, mesh: &Mesh) -> Result<(), &'static str> {
+            self.with_projected(mesh, &Mat4::identity(), |r, verts| r.fill_faces(mesh, verts, false))
+        }
+
+        fn fill_faces(&mut self, mesh: &Mesh, verts: &[Vector4], depth_only: bool) -> Result<(), &'static str> {
+            for t in mesh.triangles() {
+                self.fill_clipped(verts[t[0]], verts[t[1]], verts[t[2]], None, depth_only)?;
+            }
+
+            Ok(())
+        }
+
+        pub fn rasterize_wireframe(&mut self, mesh: &Mesh, hidden_line_removal: bool, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            self.with_projected(mesh, &Mat4::identity(), |r, verts| r.draw_edges(mesh, verts, hidden_line_removal, style))
+        }
+
+        fn draw_edges(&mut self, mesh: &Mesh, verts: &[Vector4], hidden_line_removal: bool, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            if hidden_line_removal {
+                self.fill_faces(mesh, verts, true)?;
+            }
+
+            let bias = Vector4::new(0.0, 0.0, WIREFRAME_DEPTH_BIAS, 0.0);
+            for edge in mesh.edges() {
+                self.line_with_depth(verts[edge[0]] - bias, verts[edge[1]] - bias, None, style)?;
+            }
+
+            Ok(())
+        }
+
+        pub fn render_mesh(&mut self, mesh: &Mesh) -> Result<(), &'static str> {
+            self.render_mesh_at(mesh, &Mat4::identity())
         }
 
-        pub fn get_buffer(&self) -> &Vec<T> {
-            &self.pixels
+        // Renders the mesh as if it was the child of a node with the given world matrix
+        pub fn render_mesh_at(&mut self, mesh: &Mesh, parent: &Mat4) -> Result<(), &'static str> {
+            self.with_projected(mesh, parent, |r, verts| match r.render_mode {
+                RenderMode::Solid => r.fill_faces(mesh, verts, false),
+                RenderMode::Wireframe => r.draw_edges(mesh, verts, false, None),
+                RenderMode::HiddenLine => r.draw_edges(mesh, verts, true, None),
+            })
         }
 
+        pub fn render_scene(&mut self, scene: &Scene) -> Result<(), &'static str> {
+            let meshes = scene.meshes();
+
+            // Edges have to be hidden by every mesh of the scene, not only by the ones drawn before them
+            if self.render_mode == RenderMode::HiddenLine {
+                for (mesh, world) in meshes.iter() {
+                    self.with_projected(mesh, world, |r, verts| r.fill_faces(mesh, verts, true))?;
+                }
+                for (mesh, world) in meshes.iter() {
+                    self.with_projected(mesh, world, |r, verts| r.draw_edges(mesh, verts, false, None))?;
+                }
+                return Ok(());
+            }
+
+            for (mesh, world) in meshes.iter() {
+                self.render_mesh_at(mesh, world)?;
+            }
+
+            Ok(())
+        }
     }
 
-}
+    // Twice the signed area of the triangle (a, b, p)
+    fn edge_function(a: &Vector2, b: &Vector2, p: &Vector2) -> f64 {
+        (*b - *a).perp_dot(&(*p - *a))
+    }
 
-pub mod ansi {
-    use std::{collections::HashMap, error::Error, string::FromUtf8Error};
+    fn screen_position(v: &Vector4) -> Vector2 {
+        Vector2::new(v.x() / v.w(), v.y() / v.w())
+    }
 
-    use crate::{render::Screen, render_math::vector::*};
-    use crate::render_math::Mesh;
-    use image_helper::image::*;
+    // Sutherland-Hodgman against near <= w <= far
+    fn clip_polygon(polygon: &[Vector4], near: f64, far: f64) -> Vec<Vector4> {
+        let mut result = polygon.to_vec();
 
-    pub struct ANSIRenderer {
-        helper: ANSIHelper,
+        for (sign, plane) in [(1.0, near), (-1.0, far)] {
+            let distance = |v: &Vector4| sign * (v.w() - plane);
+            let input = std::mem::take(&mut result);
 
-        currently_set_pixels: Vec<(u32, u32)>,
-        previously_set_pixels: Vec<(u32, u32)>,
+            for (i, current) in input.iter().enumerate() {
+                let next = input[(i + 1) % input.len()];
+                let (d_current, d_next) = (distance(current), distance(&next));
+                if d_current >= 0.0 {
+                    result.push(*current);
+                }
+                if (d_current >= 0.0) != (d_next >= 0.0) {
+                    result.push(current.lerp(&next, d_current / (d_current - d_next)));
+                }
+            }
+        }
 
-        z_buffer: Vec<((u32, u32), f64)>,
+        result
     }
 
-    impl ANSIRenderer {
-        pub fn new(w: u32, h: u32) -> Self {
-            ANSIRenderer {
-                helper: ANSIHelper::new(w, h),
+    // The part of the segment with near <= w <= far, None if there is none
+    fn clip_segment(from: Vector4, to: Vector4, near: f64, far: f64) -> Option<(Vector4, Vector4)> {
+        let (mut start, mut end) = (0.0, 1.0);
 
-                currently_set_pixels: Vec::new(),
-                previously_set_pixels: Vec::new(),
-
-                z_buffer: Vec::new()
+        for (sign, plane) in [(1.0, near), (-1.0, far)] {
+            let d_from = sign * (from.w() - plane);
+            let d_to = sign * (to.w() - plane);
+            if d_from < 0.0 && d_to < 0.0 {
+                return None;
+            }
+            if d_from < 0.0 {
+                start = f64::max(start, d_from / (d_from - d_to));
+            }
+            else if d_to < 0.0 {
+                end = f64::min(end, d_from / (d_from - d_to));
             }
         }
 
-        pub fn helper(&mut self) -> &mut ANSIHelper {
-            &mut self.helper
+        if start > end {
+            return None;
         }
+        Some((from.lerp(&to, start), from.lerp(&to, end)))
+    }
 
-        pub fn draw_at(&mut self, x: u32, y: u32, s: &str, z: f64, style: Option<ANSIStyle>) -> Result<(), &'static str> {
-            // Ignore pixel if something above it already exists in z buffer
-            for v in self.z_buffer.iter() {
-                if v.0.0 == x && v.0.1 == y && v.1 < z {
-                    return Ok(());
-                }
+    fn segment_point(from: Vector2, to: Vector2, t: f64) -> Vector2i {
+        Vector2i::from(&(from + (to - from) * t), Rounding::Round)
+    }
+
+    // Bresenham line, both endpoints included
+    fn line_cells(from: Vector2i, to: Vector2i) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+
+        let dx = (i64::from(to.x()) - i64::from(from.x())).abs();
+        let dy = -(i64::from(to.y()) - i64::from(from.y())).abs();
+        let step_x = if from.x() < to.x() { 1 } else { -1 };
+        let step_y = if from.y() < to.y() { 1 } else { -1 };
+        let mut error = dx + dy;
+
+        let mut x = from.x();
+        let mut y = from.y();
+        loop {
+            cells.push((x, y));
+            if x == to.x() && y == to.y() {
+                break;
             }
 
-            // WARN
-            // This if statement does not appear to have significant performance improvement
-            //if self.helper.current_pos.x() != pos.x() || self.helper.current_pos.y() != pos.y() {
-            self.helper.go_to(x, y);
-            //}
-            if let Some(st) = style {
-                self.helper.set_style(st);
+            let e2 = 2 * error;
+            if e2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if e2 <= dx {
+                error += dx;
+                y += step_y;
             }
-            self.helper.write(s)?;
-            self.currently_set_pixels.push((x, y));
+        }
 
-            self.z_buffer.push(((x, y), z));
+        cells
+    }
 
-            Ok(())
-        }
+    // Picks a glyph that follows the direction of the line. Screen y grows downwards.
+    fn slope_glyph(dx: f64, dy: f64) -> &'static str {
+        let (abs_x, abs_y) = (dx.abs(), dy.abs());
+        // tan(22.5°), splits the directions into horizontal, vertical and the two diagonals
+        let threshold = 0.4142;
 
-        pub fn set_style(&mut self, style: ANSIStyle) {
-            self.helper.set_style(style);
+        if abs_y <= abs_x * threshold {
+            "-"
+        }
+        else if abs_x <= abs_y * threshold {
+            "|"
+        }
+        else if (dx > 0.0) == (dy > 0.0) {
+            "\\"
         }
+        else {
+            "/"
+        }
+    }
 
-        pub fn clear_at(&mut self, x: u32, y: u32) {
-            self.helper.go_to(x, y);
-            _ = self.helper.write(" "); // Error doesn't matter. We are clearing the pixel.
+    fn depth_glyph(z: f64) -> &'static str {
+        if z > 16.0 {
+            "."
+        }
+        else if z > 8.0 {
+            "u"
+        }
+        else if z > 4.0 {
+            "w"
+        }
+        else if z > 2.0 {
+            "W"
         }
+        else {
+            "@"
+        }
+    }
 
-        pub fn flush(&mut self) -> Result<(), Box<dyn Error>> {
-            let previous_pixels = std::mem::take(&mut self.previously_set_pixels);
-            
-            // Clear pixels that are now unset. Does not produce flicker like full clear.
-            for previous in previous_pixels {
-                let mut found = false;
-                for current in self.currently_set_pixels.iter() {
-                    if previous.0 == current.0 && previous.1 == current.1 {
-                        found = true;
-                        break;
-                    }
-                }
-                if !found {
-                    self.clear_at(previous.0, previous.1);
-                }
+    pub struct Bitmap {
+        width: u32,
+        height: u32,
+
+        cells: Vec<char>,
+        styles: Vec<Option<ANSIStyle>>,
+        transparent: Option<char>,
+    }
+
+    impl Bitmap {
+        // Rows shorter than the widest one are padded with '\0', which is never drawn
+        pub fn new(rows: &[&str]) -> Self {
+            let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+
+            let mut cells = Vec::new();
+            for row in rows {
+                let mut chars: Vec<char> = row.chars().collect();
+                chars.resize(width, '\0');
+                cells.append(&mut chars);
             }
-            // Flush
-            self.helper.flush()?;
-            self.helper.go_to_immediate(0, 0);
-            self.previously_set_pixels = std::mem::take(&mut self.currently_set_pixels);
-            self.z_buffer.clear();
 
-            Ok(())
-        }
+            Bitmap {
+                width: width as u32,
+                height: rows.len() as u32,
 
-        pub fn go_to_immediate(&mut self, x: u32, y: u32) {
-            self.helper.go_to_immediate(x, y);
+                styles: vec![None; cells.len()],
+                cells,
+                transparent: None,
+            }
         }
 
-        pub fn go_to(&mut self, x: u32, y: u32) {
-            self.helper.go_to(x, y);
+        pub fn width(&self) -> u32 {
+            self.width
         }
-
-        pub fn clear(&mut self) {
-            self.helper.full_clear();
+        pub fn height(&self) -> u32 {
+            self.height
         }
 
-        pub fn rasterize_line(&mut self) {
+        pub fn set_transparent(&mut self, c: Option<char>) {
+            self.transparent = c;
+        }
 
+        pub fn set_style(&mut self, x: u32, y: u32, style: Option<ANSIStyle>) -> Result<(), &'static str> {
+            if x >= self.width || y >= self.height {
+                return Err("Index out of bounds.");
+            }
+            self.styles[(y * self.width + x) as usize] = style;
+            Ok(())
         }
+    }
 
-        pub fn draw_bitmap(&mut self) {
+    // A flushed frame as data: every cell and the styles active when it was written
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Frame {
+        width: u32,
+        height: u32,
+        cells: Vec<Cell>,
+        styles: Vec<Vec<ANSIStyle>>,
+    }
 
+    impl Frame {
+        pub fn width(&self) -> u32 {
+            self.width
         }
 
-        pub fn draw_image_2d(&mut self, image: &ImageData, position: Vector2i, z: f64) -> Result<(), &'static str> {
-            for y in 0..image.height() {
-                for x in 0..image.width() {
-                    let pixel = image.get_pixel_at(x, y);
+        pub fn height(&self) -> u32 {
+            self.height
+        }
 
-                    if pixel.r() != 0 {
-                        let new_pos = position + Vector2i::new(x as i32, y as i32);
-                        self.draw_at(new_pos.x() as u32, new_pos.y() as u32, "&", z, None)?;
-                    }
-                }
+        // Cells nothing was drawn to are spaces without colors
+        pub fn cell(&self, x: u32, y: u32) -> Option<&Cell> {
+            if x >= self.width {
+                return None;
             }
-
-            Ok(())
+            self.cells.get((y * self.width + x) as usize)
         }
 
-        pub fn rasterize_vertices(&mut self, mesh: &Mesh, max_distance: f64) -> Result<(), &'static str> {
-            let verts = mesh.get_transformed_verts();
-
-            for x in 0..self.helper.width {
-                for y in 0..self.helper.height {
-                    for vert in verts.iter() {
-                        let pixel_pos_3d = Vector3::new(f64::from(x), f64::from(y), 0.0);
-                        let orthographic_projection_vector = Vector3::new(vert.x(), vert.y(), 0.0);
-                        if (orthographic_projection_vector - pixel_pos_3d).magnitude() <= max_distance {
-                            let mut c = "@";
-
-                            if vert.z() > 16.0 {
-                                c = ".";
-                            }
-                            else if vert.z() > 8.0 {
-                                c = "u";
-                            }
-                            else if vert.z() > 4.0 {
-                                c = "w";
-                            }
-                            else if vert.z() > 2.0 {
-                                c = "W";
-                            }
+        pub fn glyph(&self, x: u32, y: u32) -> Option<char> {
+            self.cell(x, y).map(|c| c.glyph)
+        }
 
-                            self.draw_at(x, y, c, vert.z(), None)?;
-                        }
-                    }
-                }
+        pub fn styles(&self, x: u32, y: u32) -> Option<&[ANSIStyle]> {
+            if x >= self.width {
+                return None;
             }
+            self.styles.get((y * self.width + x) as usize).map(|s| s.as_slice())
+        }
 
-            Ok(())
+        // One line per row, each ending with a newline
+        pub fn to_text(&self) -> String {
+            let mut text = String::with_capacity(self.cells.len() + self.height as usize);
+            for row in self.cells.chunks(self.width.max(1) as usize) {
+                text.extend(row.iter().map(|c| c.glyph));
+                text.push('\n');
+            }
+            text
         }
     }
 
-    pub struct ANSIHelper {
+    pub struct ANSIHelper<W: Write = Stdout> {
         x: u32,
         y: u32,
 
         width: u32,
         height: u32,
 
-        screen: Screen<char>,
-        aux_buffer: HashMap<(u32, u32), String>
+        screen: Screen<Cell>,
+        aux_buffer: HashMap<(u32, u32), String>,
+
+        // Colors given to every cell written from now on
+        foreground: Option<Color>,
+        background: Option<Color>,
+
+        output: W,
+        // Every frame is assembled here first and written to output at once. Kept to reuse its allocation.
+        frame: String,
+
+        // Only tracked while recording. Style changes are keyed by cell index, styles stay active across frames like in a terminal.
+        recording: bool,
+        style_changes: HashMap<u32, Vec<ANSIStyle>>,
+        active_styles: Vec<ANSIStyle>,
+        recorded: Option<Frame>,
     }
 
-    impl ANSIHelper {
+    impl ANSIHelper<Stdout> {
         pub fn new(w: u32, h: u32) -> Self {
+            ANSIHelper::with_output(w, h, io::stdout())
+        }
+    }
+
+    impl<W: Write> ANSIHelper<W> {
+        pub fn with_output(w: u32, h: u32, output: W) -> Self {
             ANSIHelper {
                 x: 0,
                 y: 0,
@@ -700,12 +5403,89 @@ pub mod ansi {
 
                 screen: Screen::new(w, h),
                 aux_buffer: HashMap::new(),
+
+                foreground: None,
+                background: None,
+
+                output,
+                frame: String::new(),
+
+                recording: false,
+                style_changes: HashMap::new(),
+                active_styles: Vec::new(),
+                recorded: None,
+            }
+        }
+
+        pub fn set_recording(&mut self, enabled: bool) {
+            self.recording = enabled;
+            self.style_changes.clear();
+            if !enabled {
+                self.recorded = None;
+            }
+        }
+
+        pub fn frame(&self) -> Option<&Frame> {
+            self.recorded.as_ref()
+        }
+
+        fn record_frame(&mut self) {
+            let buffer = self.screen.get_buffer();
+            let mut cells = Vec::with_capacity(buffer.len());
+            let mut styles = Vec::with_capacity(buffer.len());
+
+            for (i, c) in buffer.iter().enumerate() {
+                for style in self.style_changes.remove(&(i as u32)).unwrap_or_default() {
+                    if style == ANSIStyle::None {
+                        self.active_styles.clear();
+                    }
+                    else if !self.active_styles.contains(&style) {
+                        self.active_styles.push(style);
+                    }
+                }
+
+                if c.glyph == '\0' {
+                    cells.push(Cell::new(' ', None, None));
+                    styles.push(Vec::new());
+                }
+                else {
+                    cells.push(*c);
+                    styles.push(self.active_styles.clone());
+                }
             }
+            self.style_changes.clear();
+
+            self.recorded = Some(Frame {
+                width: self.width,
+                height: self.height,
+                cells,
+                styles,
+            });
+        }
+
+        pub fn output(&self) -> &W {
+            &self.output
+        }
+
+        pub fn mut_output(&mut self) -> &mut W {
+            &mut self.output
+        }
+
+        // None is the terminal's default color
+        pub fn set_colors(&mut self, foreground: Option<Color>, background: Option<Color>) {
+            self.foreground = foreground;
+            self.background = background;
+        }
+
+        pub fn colors(&self) -> (Option<Color>, Option<Color>) {
+            (self.foreground, self.background)
         }
 
         pub fn write(&mut self, text: &str) -> Result<(), &'static str> {
-            self.screen.push_pixels(self.x, self.y, Vec::from_iter(text.chars()))?;
-            self.advance(text.len() as u32);
+            let cells = text.chars().map(|c| Cell::new(c, self.foreground, self.background)).collect();
+            self.screen.push_pixels(self.x, self.y, cells)?;
+            // One cell per char, len() would count the bytes of multi-byte glyphs
+            self.advance(text.chars().count() as u32);
             
             Ok(())
         }
@@ -727,27 +5507,40 @@ pub mod ansi {
             self.aux_buffer.insert((x, y), String::from(text));
         }
 
-        pub fn flush(&mut self) -> Result<(), FromUtf8Error> {
-            self.go_to_immediate(0, 0);
-            //print!("{}", self.screen.get_buffer().as_slice()); // Maybe there's a way to make it work?
+        pub fn flush(&mut self) -> io::Result<()> {
+            if self.recording {
+                self.record_frame();
+            }
+
+            self.frame.clear();
+            self.frame.push_str("\u{001B}[0;0H");
             self.x = 0;
             self.y = 0;
 
             let mut goto = false;
+            // Colors the terminal is currently set to, None after any escape from the aux buffer since it may have changed them
+            let mut emitted: Option<(Option<Color>, Option<Color>)> = Some((None, None));
 
             for c in self.screen.get_buffer() {
                 if let Some(aux) = self.aux_buffer.get(&(self.x, self.y)) {
-                    print!("{aux}");
+                    self.frame.push_str(aux);
+                    emitted = None;
                 }
-                if *c == '\0' {
+                if c.glyph == '\0' {
                     goto = true;
                 }
                 else {
                     if goto {
-                        print!("\u{001B}[{};{}H", self.y, self.x);
+                        _ = write!(self.frame, "\u{001B}[{};{}H", self.y, self.x);
                         goto = false;
                     }
-                    print!("{}", c);
+                    if emitted != Some((c.foreground, c.background)) {
+                        let foreground = c.foreground.map_or(Style::Attribute(ANSIStyle::DefaultForegroundColor), Style::Foreground);
+                        let background = c.background.map_or(Style::Attribute(ANSIStyle::DefaultBackgroundColor), Style::Background);
+                        self.frame.push_str(&Style::sequence(&[foreground, background]));
+                        emitted = Some((c.foreground, c.background));
+                    }
+                    self.frame.push(c.glyph);
                 }
                 
                 // Rust borrow checker moment. Can't use self functions
@@ -756,11 +5549,21 @@ pub mod ansi {
                     self.x -= self.width;
                     self.y += 1;
 
-                    print!("\u{001B}[{};{}H", self.y, self.x);
+                    _ = write!(self.frame, "\u{001B}[{};{}H", self.y, self.x);
                 }
             }
+            if emitted != Some((None, None)) {
+                self.frame.push_str(&Style::sequence(&[ANSIStyle::DefaultForegroundColor.into(), ANSIStyle::DefaultBackgroundColor.into()]));
+            }
+            // Leave the cursor home, where the next frame starts
+            self.frame.push_str("\u{001B}[0;0H");
+            self.x = 0;
+            self.y = 0;
             self.screen.clear();
-            Ok(())
+            self.aux_buffer.clear();
+
+            self.output.write_all(self.frame.as_bytes())?;
+            self.output.flush()
         }
 
         pub fn go_to(&mut self, x: u32, y: u32) {
@@ -768,16 +5571,25 @@ pub mod ansi {
             self.y = y;
         }
 
-        pub fn go_to_immediate(&mut self, x: u32, y: u32) {
+        pub fn go_to_immediate(&mut self, x: u32, y: u32) -> io::Result<()> {
             self.x = x;
             self.y = y;
-            self.csi_start_immediate();
-            print!("{y};{x}H");
+            self.csi_start_immediate()?;
+            write!(self.output, "{y};{x}H")
         }
         
+        // Legacy colors like SetForegroundColor only last until the next cell, flush writes every cell's
+        // own colors after an escape. Use set_colors instead. SetForegroundColor8bit and
+        // SetBackgroundColor8bit need a color as argument and are ignored, see Style::Foreground.
         pub fn set_style(&mut self, style: ANSIStyle) {
+            if style.needs_arguments() {
+                return;
+            }
+            if self.recording {
+                self.style_changes.entry(self.y * self.width + self.x).or_default().push(style);
+            }
             self.csi_start();
-            self.write_aux(self.x, self.y, &((style as i32).to_string() + "m"));
+            self.write_aux(self.x, self.y, &(Style::Attribute(style).sgr() + "m"));
         }
 
         pub fn carriage_return(&mut self) {
@@ -788,14 +5600,14 @@ pub mod ansi {
             self.write_aux(self.x, self.y, "\u{0007}");
         }
 
-        pub fn full_clear(&mut self) {
-            self.csi_start_immediate();
-            print!("0J");
+        pub fn full_clear(&mut self) -> io::Result<()> {
+            self.csi_start_immediate()?;
+            write!(self.output, "0J")
         }
 
-        pub fn csi_start_immediate(&mut self) {
-            self.escape_start_immediate();
-            print!("[");
+        pub fn csi_start_immediate(&mut self) -> io::Result<()> {
+            self.escape_start_immediate()?;
+            write!(self.output, "[")
         }
 
         pub fn csi_start(&mut self) {
@@ -803,8 +5615,8 @@ pub mod ansi {
             self.write_aux(self.x, self.y, "["); // TODO: potentially improve error handling
         }
         
-        pub fn escape_start_immediate(&mut self) {
-            print!("\u{001B}");
+        pub fn escape_start_immediate(&mut self) -> io::Result<()> {
+            write!(self.output, "\u{001B}")
         }
 
         pub fn escape_start(&mut self) {
@@ -812,6 +5624,132 @@ pub mod ansi {
         }
     }
 
+    // Glyph and colors of one cell of the screen
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct Cell {
+        glyph: char,
+        foreground: Option<Color>,
+        background: Option<Color>,
+    }
+
+    impl Cell {
+        pub fn new(glyph: char, foreground: Option<Color>, background: Option<Color>) -> Self {
+            Cell {
+                glyph,
+                foreground,
+                background,
+            }
+        }
+
+        pub fn glyph(&self) -> char {
+            self.glyph
+        }
+        pub fn foreground(&self) -> Option<Color> {
+            self.foreground
+        }
+        pub fn background(&self) -> Option<Color> {
+            self.background
+        }
+    }
+
+    // '\0' marks a cell nothing was written to
+    impl Default for Cell {
+        fn default() -> Self {
+            Cell::new('\0', None, None)
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    pub enum Color {
+        Black,
+        Red,
+        Green,
+        Yellow,
+        Blue,
+        Magenta,
+        Cyan,
+        White,
+        BrightBlack,
+        BrightRed,
+        BrightGreen,
+        BrightYellow,
+        BrightBlue,
+        BrightMagenta,
+        BrightCyan,
+        BrightWhite,
+        // Index into the terminal's 256 color palette
+        Palette(u8),
+        Rgb(u8, u8, u8),
+    }
+
+    impl Color {
+        // SGR parameters for this color. base is the code of black and extended the code
+        // that takes palette and rgb arguments (38 for the foreground, 48 for the background).
+        fn parameters(self, base: u8, extended: u8) -> String {
+            let named = |i: u8| (base + i).to_string();
+            let bright = |i: u8| (base + 60 + i).to_string();
+
+            match self {
+                Color::Black => named(0),
+                Color::Red => named(1),
+                Color::Green => named(2),
+                Color::Yellow => named(3),
+                Color::Blue => named(4),
+                Color::Magenta => named(5),
+                Color::Cyan => named(6),
+                Color::White => named(7),
+                Color::BrightBlack => bright(0),
+                Color::BrightRed => bright(1),
+                Color::BrightGreen => bright(2),
+                Color::BrightYellow => bright(3),
+                Color::BrightBlue => bright(4),
+                Color::BrightMagenta => bright(5),
+                Color::BrightCyan => bright(6),
+                Color::BrightWhite => bright(7),
+                Color::Palette(i) => format!("{extended};5;{i}"),
+                Color::Rgb(r, g, b) => format!("{extended};2;{r};{g};{b}"),
+            }
+        }
+    }
+
+    // One complete SGR parameter, including the arguments ANSIStyle can't carry
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub enum Style {
+        Attribute(ANSIStyle),
+        Foreground(Color),
+        Background(Color),
+    }
+
+    impl Style {
+        // The parameters without the CSI and the final 'm', e.g. "38;2;255;0;0".
+        // Empty for attributes that can't be written without arguments.
+        pub fn sgr(&self) -> String {
+            match self {
+                Style::Attribute(style) if style.needs_arguments() => String::new(),
+                Style::Attribute(style) => (*style as i32).to_string(),
+                Style::Foreground(color) => color.parameters(30, 38),
+                Style::Background(color) => color.parameters(40, 48),
+            }
+        }
+
+        // Full escape sequence that applies the styles in order, empty if there is nothing to apply
+        pub fn sequence(styles: &[Style]) -> String {
+            let parameters: Vec<String> = styles.iter().map(|s| s.sgr()).filter(|p| !p.is_empty()).collect();
+            if parameters.is_empty() {
+                // An empty parameter list would reset everything
+                return String::new();
+            }
+            format!("\u{001B}[{}m", parameters.join(";"))
+        }
+    }
+
+    impl From<ANSIStyle> for Style {
+        fn from(style: ANSIStyle) -> Self {
+            Style::Attribute(style)
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
     pub enum ANSIStyle {
         None = 0,
         Bold,
@@ -861,4 +5799,344 @@ pub mod ansi {
         SetBrightForegroundColor,
         SetBrightBackgroundColor = 100,
     }
+
+    impl ANSIStyle {
+        // 38 and 48 are followed by the color, which only Style::Foreground and Style::Background carry
+        fn needs_arguments(self) -> bool {
+            matches!(self, ANSIStyle::SetForegroundColor8bit | ANSIStyle::SetBackgroundColor8bit)
+        }
+    }
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Headless 20x10 renderer with a cube of size 2 at the origin, 3 units in front of the camera.
+        // Cell (10, 5) is in the middle of the cube's front face.
+        fn renderer(convention: DepthConvention) -> ANSIRenderer<Sink> {
+            let mut renderer = ANSIRenderer::headless(20, 10);
+            renderer.set_depth_convention(convention);
+
+            let mut camera = Camera::for_terminal(20, 10);
+            camera.set_position(Vector3::new(0.0, 0.0, 4.0));
+            renderer.set_camera(Some(camera));
+            renderer
+        }
+
+        fn flushed_glyph(renderer: &mut ANSIRenderer<Sink>, x: u32, y: u32) -> char {
+            renderer.flush().unwrap();
+            renderer.frame().unwrap().glyph(x, y).unwrap()
+        }
+
+        // Floor at y = -1 from behind the camera far into the distance, seen from the origin on a 40x20 screen
+        fn floor_renderer() -> ANSIRenderer<Sink> {
+            let mut renderer = ANSIRenderer::headless(40, 20);
+            renderer.set_camera(Some(Camera::for_terminal(40, 20)));
+
+            let mut floor = Mesh::new();
+            floor.add_vertex(Vector3::new(-10.0, -1.0, 10.0));
+            floor.add_vertex(Vector3::new(10.0, -1.0, 10.0));
+            floor.add_vertex(Vector3::new(10.0, -1.0, -50.0));
+            floor.add_vertex(Vector3::new(-10.0, -1.0, -50.0));
+            floor.add_face(&[0, 1, 2, 3]).unwrap();
+
+            renderer.render_mesh(&floor).unwrap();
+            renderer
+        }
+
+        #[test]
+        fn triangles_crossing_the_near_plane_are_clipped_not_dropped() {
+            let mut r = floor_renderer();
+            r.flush().unwrap();
+            let frame = r.frame().unwrap();
+            assert_ne!(frame.glyph(20, 15), Some(' '));
+            assert_ne!(frame.glyph(20, 19), Some(' '));
+        }
+
+        #[test]
+        fn depth_is_perspective_correct() {
+            let r = floor_renderer();
+            // The ray through row 15 hits the floor at a distance of f * half height / (15 - half height)
+            let f = 1.0 / (std::f64::consts::FRAC_PI_3 / 2.0).tan();
+            let expected = f * 10.0 / 5.0;
+            let depth = *r.depth_buffer.get_pixel(20, 15).unwrap();
+            assert!((depth - expected).abs() < 1e-9, "{depth} != {expected}");
+        }
+
+        #[test]
+        fn hidden_line_scene_hides_edges_behind_later_meshes() {
+            let scene_frame = |far_first: bool| {
+                let mut scene = Scene::new();
+                let far = Mat4::translation(Vector3::new(0.0, 0.0, -3.0));
+                let near = Mat4::identity();
+                let order = if far_first { [(far, 1.0), (near, 2.0)] } else { [(near, 2.0), (far, 1.0)] };
+                for (i, (transform, size)) in order.into_iter().enumerate() {
+                    scene.add_node(&i.to_string(), None, transform, Some(Mesh::cube(size))).unwrap();
+                }
+
+                let mut r = renderer(DepthConvention::NearIsSmaller);
+                r.set_render_mode(RenderMode::HiddenLine);
+                r.render_scene(&scene).unwrap();
+                r.flush().unwrap();
+                r.frame().unwrap().to_text()
+            };
+
+            let mut only_near = renderer(DepthConvention::NearIsSmaller);
+            only_near.set_render_mode(RenderMode::HiddenLine);
+            only_near.render_mesh(&Mesh::cube(2.0)).unwrap();
+            only_near.flush().unwrap();
+            let expected = only_near.frame().unwrap().to_text();
+
+            assert_eq!(scene_frame(true), expected);
+            assert_eq!(scene_frame(false), expected);
+        }
+
+        #[test]
+        fn near_is_smaller_keeps_smallest_draw_at_depth() {
+            let mut r = renderer(DepthConvention::NearIsSmaller);
+            r.draw_at(0, 0, "A", 1.0, None).unwrap();
+            r.draw_at(0, 0, "B", 2.0, None).unwrap();
+            assert_eq!(flushed_glyph(&mut r, 0, 0), 'A');
+        }
+
+        #[test]
+        fn reversed_z_keeps_largest_draw_at_depth() {
+            let mut r = renderer(DepthConvention::ReversedZ);
+            r.draw_at(0, 0, "A", 1.0, None).unwrap();
+            r.draw_at(0, 0, "B", 2.0, None).unwrap();
+            assert_eq!(flushed_glyph(&mut r, 0, 0), 'B');
+        }
+
+        #[test]
+        fn equal_depth_is_overwritten_by_default() {
+            for convention in [DepthConvention::NearIsSmaller, DepthConvention::ReversedZ] {
+                let mut r = renderer(convention);
+                r.draw_at(0, 0, "A", 1.0, None).unwrap();
+                r.draw_at(0, 0, "B", 1.0, None).unwrap();
+                assert_eq!(flushed_glyph(&mut r, 0, 0), 'B');
+            }
+        }
+
+        #[test]
+        fn near_is_smaller_compares_draw_at_with_mesh_distance() {
+            let mut r = renderer(DepthConvention::NearIsSmaller);
+            r.render_mesh(&Mesh::cube(2.0)).unwrap();
+            r.draw_at(10, 5, "N", 1.0, None).unwrap();
+            r.draw_at(9, 5, "F", 10.0, None).unwrap();
+            r.flush().unwrap();
+
+            let frame = r.frame().unwrap();
+            assert_eq!(frame.glyph(10, 5), Some('N'));
+            assert_ne!(frame.glyph(9, 5), Some('F'));
+            assert_ne!(frame.glyph(9, 5), Some(' '));
+        }
+
+        #[test]
+        fn reversed_z_stores_mesh_distance_negated() {
+            let mut r = renderer(DepthConvention::ReversedZ);
+            r.render_mesh(&Mesh::cube(2.0)).unwrap();
+            // draw_at z is stored as is, so its depth has to be given in the convention
+            r.draw_at(10, 5, "N", DepthConvention::ReversedZ.encode(1.0), None).unwrap();
+            r.draw_at(9, 5, "F", DepthConvention::ReversedZ.encode(10.0), None).unwrap();
+            // A raw distance is positive and therefore in front of every mesh
+            r.draw_at(11, 5, "R", 10.0, None).unwrap();
+            r.flush().unwrap();
+
+            let frame = r.frame().unwrap();
+            assert_eq!(frame.glyph(10, 5), Some('N'));
+            assert_ne!(frame.glyph(9, 5), Some('F'));
+            assert_eq!(frame.glyph(11, 5), Some('R'));
+        }
+
+        #[test]
+        fn mesh_drawn_after_draw_at_is_depth_tested_against_it() {
+            for convention in [DepthConvention::NearIsSmaller, DepthConvention::ReversedZ] {
+                let mut r = renderer(convention);
+                r.draw_at(10, 5, "N", convention.encode(1.0), None).unwrap();
+                r.draw_at(9, 5, "F", convention.encode(10.0), None).unwrap();
+                r.render_mesh(&Mesh::cube(2.0)).unwrap();
+                r.flush().unwrap();
+
+                let frame = r.frame().unwrap();
+                assert_eq!(frame.glyph(10, 5), Some('N'));
+                assert_ne!(frame.glyph(9, 5), Some('F'));
+            }
+        }
+
+        // The example's image, placed so that one of its drawn pixels lands on cell (10, 5)
+        fn image_over_centre() -> (ImageData, Vector2i) {
+            let image = read_image_from_file("LoveYou.png").unwrap();
+            let (x, y) = (0..image.height())
+                .flat_map(|y| (0..image.width()).map(move |x| (x, y)))
+                .find(|&(x, y)| image.get_pixel_at(x, y).r() != 0)
+                .unwrap();
+            (image, Vector2i::new(10 - x as i32, 5 - y as i32))
+        }
+
+        #[test]
+        fn image_is_depth_tested_against_draw_at_and_meshes() {
+            let (image, position) = image_over_centre();
+
+            for convention in [DepthConvention::NearIsSmaller, DepthConvention::ReversedZ] {
+                // Like draw_at, the image's z is stored as is and has to be given in the convention
+                let mut r = renderer(convention);
+                r.render_mesh(&Mesh::cube(2.0)).unwrap();
+                r.draw_image_2d(&image, position, convention.encode(1.0)).unwrap();
+                assert_eq!(flushed_glyph(&mut r, 10, 5), '&');
+
+                r.draw_image_2d(&image, position, convention.encode(10.0)).unwrap();
+                r.render_mesh(&Mesh::cube(2.0)).unwrap();
+                assert_ne!(flushed_glyph(&mut r, 10, 5), '&');
+
+                r.draw_at(10, 5, "N", convention.encode(1.0), None).unwrap();
+                r.draw_image_2d(&image, position, convention.encode(2.0)).unwrap();
+                assert_eq!(flushed_glyph(&mut r, 10, 5), 'N');
+
+                r.draw_image_2d(&image, position, convention.encode(2.0)).unwrap();
+                r.draw_at(10, 5, "F", convention.encode(3.0), None).unwrap();
+                assert_eq!(flushed_glyph(&mut r, 10, 5), '&');
+            }
+        }
+
+        #[test]
+        fn aux_escapes_are_only_emitted_in_their_own_frame() {
+            let mut helper = ANSIHelper::with_output(4, 1, Vec::new());
+            helper.beep();
+            helper.write("ab").unwrap();
+            helper.flush().unwrap();
+            assert!(helper.output().contains(&0x07));
+
+            helper.mut_output().clear();
+            helper.write("ab").unwrap();
+            helper.flush().unwrap();
+            assert!(!helper.output().contains(&0x07));
+        }
+
+        #[test]
+        fn colors_are_emitted_again_after_a_legacy_color_escape() {
+            let mut helper = ANSIHelper::with_output(4, 1, Vec::new());
+            helper.set_style(ANSIStyle::SetForegroundColor);
+            helper.write("a").unwrap();
+            helper.flush().unwrap();
+
+            let output = String::from_utf8(helper.output().clone()).unwrap();
+            let legacy = output.find("\u{001B}[30m").unwrap();
+            let defaults = output.find("\u{001B}[39;49m").unwrap();
+            let glyph = output.find('a').unwrap();
+            assert!(legacy < defaults && defaults < glyph);
+        }
+
+        #[test]
+        fn color_attributes_without_arguments_are_not_emitted() {
+            let mut helper = ANSIHelper::with_output(4, 1, Vec::new());
+            helper.set_style(ANSIStyle::SetForegroundColor8bit);
+            helper.set_style(ANSIStyle::SetBackgroundColor8bit);
+            helper.write("a").unwrap();
+            helper.flush().unwrap();
+
+            let output = String::from_utf8(helper.output().clone()).unwrap();
+            assert!(!output.contains("38m") && !output.contains("48m"));
+            assert_eq!(Style::sequence(&[ANSIStyle::SetForegroundColor8bit.into()]), "");
+            assert_eq!(Style::sequence(&[ANSIStyle::SetBackgroundColor8bit.into(), ANSIStyle::Bold.into()]), "\u{001B}[1m");
+        }
+
+        #[test]
+        fn occluded_line_cells_do_not_reset_the_style() {
+            let mut r = ANSIRenderer::headless(10, 3);
+            r.draw_at(0, 1, "A", 1.0, None).unwrap();
+            r.draw_at(5, 0, "U", 1.0, Some(ANSIStyle::Underline)).unwrap();
+            // Entirely behind A, so nothing is drawn and the cursor stays right after U
+            r.rasterize_line(Vector2i::new(0, 1), Vector2i::new(0, 1), 5.0, Some("x"), Some(ANSIStyle::Bold)).unwrap();
+            r.draw_at(6, 0, "V", 1.0, None).unwrap();
+            r.flush().unwrap();
+
+            let frame = r.frame().unwrap();
+            assert_eq!(frame.glyph(0, 1), Some('A'));
+            assert_eq!(frame.styles(6, 0), Some(&[ANSIStyle::Underline][..]));
+        }
+
+        // Cleared cells are the only spaces written to the output
+        fn cleared_cells(r: &mut ANSIRenderer<Vec<u8>>) -> usize {
+            r.flush().unwrap();
+            let cleared = r.helper().output().iter().filter(|&&b| b == b' ').count();
+            r.helper().mut_output().clear();
+            cleared
+        }
+
+        #[test]
+        fn flush_clears_cells_that_were_not_drawn_again() {
+            let mut r = ANSIRenderer::with_output(4, 2, Vec::new());
+            r.draw_at(1, 0, "A", 0.0, None).unwrap();
+            r.draw_at(1, 0, "A", 0.0, None).unwrap();
+            assert_eq!(cleared_cells(&mut r), 0);
+
+            // Overdrawn twice, but only A's cell is cleared, once
+            r.draw_at(2, 1, "B", 0.0, None).unwrap();
+            r.draw_at(2, 1, "B", 0.0, None).unwrap();
+            assert_eq!(cleared_cells(&mut r), 1);
+
+            r.draw_at(2, 1, "B", 0.0, None).unwrap();
+            assert_eq!(cleared_cells(&mut r), 0);
+            assert_eq!(cleared_cells(&mut r), 1);
+            assert_eq!(cleared_cells(&mut r), 0);
+        }
+
+        #[test]
+        fn lines_with_far_off_screen_endpoints_are_clipped_to_the_screen() {
+            let mut r = ANSIRenderer::headless(20, 10);
+            r.rasterize_line_3d(Vector3::new(-1e12, 3.0, 1.0), Vector3::new(1e12, 3.0, 1.0), None, None).unwrap();
+            r.rasterize_line(Vector2i::new(i32::MIN, i32::MIN), Vector2i::new(i32::MAX, i32::MAX), 0.0, Some("\\"), None).unwrap();
+            // Never crosses the screen
+            r.rasterize_line_3d(Vector3::new(-1e12, -5.0, 1.0), Vector3::new(1e12, -5.0, 1.0), Some("x"), None).unwrap();
+            r.flush().unwrap();
+
+            let frame = r.frame().unwrap();
+            for x in 0..20 {
+                let expected = if x == 3 { '\\' } else { '-' };
+                assert_eq!(frame.glyph(x, 3), Some(expected));
+            }
+            for i in 0..10 {
+                assert_eq!(frame.glyph(i, i), Some('\\'));
+            }
+            assert!(!frame.to_text().contains('x'));
+        }
+
+        #[test]
+        fn bitmap_style_does_not_bleed_past_multi_byte_glyphs() {
+            let mut bitmap = Bitmap::new(&["█ab"]);
+            bitmap.set_style(0, 0, Some(ANSIStyle::Bold)).unwrap();
+
+            let mut r = ANSIRenderer::headless(4, 1);
+            r.draw_bitmap(&bitmap, Vector2i::new(0, 0), 0.0).unwrap();
+            r.flush().unwrap();
+
+            let frame = r.frame().unwrap();
+            assert_eq!(frame.to_text(), "█ab \n");
+            assert_eq!(frame.styles(0, 0), Some(&[ANSIStyle::Bold][..]));
+            assert_eq!(frame.styles(1, 0), Some(&[][..]));
+            assert_eq!(frame.styles(2, 0), Some(&[][..]));
+        }
+
+        #[test]
+        fn draw_at_clears_every_cell_of_a_shorter_string() {
+            let mut r = ANSIRenderer::with_output(6, 2, Vec::new());
+            r.draw_at(0, 0, "1234", 0.0, None).unwrap();
+            assert_eq!(cleared_cells(&mut r), 0);
+
+            r.draw_at(0, 0, "12", 0.0, None).unwrap();
+            assert_eq!(cleared_cells(&mut r), 2);
+        }
+
+        #[test]
+        fn draw_at_depth_tests_every_cell() {
+            let mut r = ANSIRenderer::headless(3, 2);
+            r.draw_at(1, 0, "X", 1.0, None).unwrap();
+            r.draw_at(1, 1, "Y", 1.0, None).unwrap();
+            // Wraps onto the second row, behind X and Y, and the rest is past the end of the screen
+            assert!(r.draw_at(0, 0, "abcdefgh", 5.0, None).unwrap());
+            assert!(!r.draw_at(1, 0, "Z", 5.0, None).unwrap());
+            r.flush().unwrap();
+
+            assert_eq!(r.frame().unwrap().to_text(), "aXc\ndYf\n");
+        }
+    }
 }
\ No newline at end of file